@@ -0,0 +1,572 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::lib::{
+    calibration::{self, FieldProfile},
+    check, flip,
+    manifest::{self, MANIFEST_NAME},
+    plan::WritePlan,
+    symmetry,
+    variants::{self, Variant},
+    watch::{self, Watcher},
+};
+use crate::FlipFileType;
+
+const USAGE: &str = "usage: fldmirr flip <input> <output> [options]
+       fldmirr reverse <input> <output> [--latest] [--dry-run] [--force]
+       fldmirr variants <input> [options]
+       fldmirr regen [<folder>] [--dry-run] [--force]
+       fldmirr calibrate (<profile> | --nominal) [<folder>] [--precision <m>[,<rad>]]
+                         [--dry-run] [--force]
+       fldmirr watch [<folder>]
+       fldmirr check [<folder>] [--tolerance <x>]
+       fldmirr compare <file> <mirror> [--tolerance <m>] [--heading-tolerance <deg>]
+
+flip
+  <input>     a choreo .traj or pathplanner .path file
+  <output>    where to write the flipped file
+  --alliance  flip to the other alliance instead of the other side of the same alliance
+  --mirror-headings  mirror headings across the center line with --alliance too, instead
+                     of keeping them as they were
+  --latest    write the latest file version instead of the input's version
+  --reverse   also run it from its end to its start
+  --precision <m>[,<rad>]  round written lengths and angles to these steps
+                           (default: 1e-9 for both)
+
+  for .traj files only
+  --time-scale <x>  stretch the trajectory in time, > 1 is slower (default: 1)
+  --resample <dt>   resample it to intervals no longer than dt seconds
+
+  instead of flipping, any number of these steps can be applied in order, with lengths
+  in meters and angles in degrees counterclockwise. (x,y) defaults to the center of the
+  field the project is calibrated to
+  --translate <dx>,<dy>
+  --rotate <degrees>[,<x>,<y>]     about (x,y)
+  --reflect <degrees>[,<x>,<y>]    across the line through (x,y) at that angle
+
+reverse
+  writes <input> run from its end to its start, for the way back along it, without
+  flipping it. Waypoints, rotation targets, zones, markers, constraints and events are
+  turned around with it
+
+variants
+  makes several flips of a .traj, .path or .auto at once, next to it. Each is named by
+  its own rule, in which {name} stands for the name of the file flipped, and an auto's
+  paths are flipped and named along with it. Without any of these every one is made
+  --same-alliance[=<rule>]  default: {name}_Mirrored
+  --alliance[=<rule>]       default: {name}_OtherAlliance
+  --both[=<rule>]           the same alliance flip, then the alliance flip
+                            default: {name}_OtherAllianceMirrored
+  --latest, --mirror-headings, --dry-run and --force work as they do for flip
+
+regen
+  rebuilds every flipped file whose source changed since it was flipped, from the
+  .pathflip.json manifest of the project <folder> (default: the current folder) is in
+
+  --dry-run   list the files that would be created, overwritten or modified, and stop
+  --force     overwrite existing files
+
+calibrate
+  moves every path, trajectory and auto of the project <folder> (default: the current
+  folder) is in onto the field measured at a venue, and flips from then on about its
+  center. The flipped files in the manifest are flipped again from the moved sources
+  <profile>   a .json field profile: {\"name\", \"length\", \"width\"} in meters, and
+              optionally \"origin\": [x, y] and \"elements\": [{\"name\", \"x\", \"y\",
+              \"radius\", \"dx\", \"dy\"}] for elements measured off where they should be
+  --nominal   back to the nominal field instead
+  --precision works as it does for flip, and only files that move are written
+  --dry-run and --force work as they do for regen
+
+watch
+  regenerates flipped files every time one of their sources is saved, until stopped
+  with ctrl-c. Outputs listed in the manifest are overwritten without asking
+
+check
+  fails when a flipped file in the manifest doesn't match the flip of its source as it is
+  now, is missing, or when a file looks flipped but isn't in the manifest. Nothing is
+  written, so this can run as part of a build
+
+  --tolerance <x>  how far numbers may differ and still match (default: 1e-6)
+
+compare
+  reports how far <mirror> is from the same alliance, alliance and rotational flips of
+  <file>, by waypoint and along the route. Fails unless the closest one is within
+  tolerance everywhere
+
+  --tolerance <m>            position error allowed (default: 0.01)
+  --heading-tolerance <deg>  heading error allowed (default: 1)
+
+run without arguments to open the window";
+
+// everything after the program name. Problems go to stderr: 2 for bad arguments, 1 when
+// the flip itself failed
+pub fn run(args: &[String]) -> ExitCode {
+    let (cmd, rest) = match args.split_first() {
+        Some((cmd, rest)) => (cmd.as_str(), rest),
+        None => return usage(None),
+    };
+    match cmd {
+        "flip" => flip(rest, flip::FlipOptions::default()),
+        "reverse" => flip(
+            rest,
+            flip::FlipOptions {
+                reverse: true,
+                in_place: true,
+                ..Default::default()
+            },
+        ),
+        "variants" => make_variants(rest),
+        "regen" => regen(rest),
+        "calibrate" => calibrate(rest),
+        "watch" => watch(rest),
+        "check" => check(rest),
+        "compare" => compare(rest),
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => usage(Some(&format!("unknown command {}", cmd))),
+    }
+}
+
+// `opts` as flip or reverse starts them, before the options given
+fn flip(args: &[String], mut opts: flip::FlipOptions) -> ExitCode {
+    let mut write = WriteArgs::default();
+    let mut files = Vec::new();
+    // resolved once the input's field is known
+    let mut steps = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--translate" | "--rotate" | "--reflect" => match args.next() {
+                Some(value) => steps.push((arg.as_str(), value.as_str())),
+                None => return usage(Some(&format!("bad value for {}", arg))),
+            },
+            "--precision" => match args.next().and_then(|v| precision(v)) {
+                Some(precision) => opts.precision = precision,
+                None => return usage(Some("--precision takes positive steps in m and rad")),
+            },
+            "--alliance" => opts.same_alliance = false,
+            "--mirror-headings" => opts.mirror_headings = true,
+            "--reverse" => opts.reverse = true,
+            "--time-scale" => match args.next().and_then(|x| x.parse::<f64>().ok()) {
+                Some(x) if x > 0.0 => opts.time_scale = x,
+                _ => return usage(Some("--time-scale takes a positive number")),
+            },
+            "--resample" => match args.next().and_then(|x| x.parse::<f64>().ok()) {
+                Some(dt) if dt > 0.0 => opts.resample_dt = Some(dt),
+                _ => return usage(Some("--resample takes a positive number of seconds")),
+            },
+            "--latest" => opts.write_latest = true,
+            "--dry-run" => write.dry_run = true,
+            "--force" => write.force = true,
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            file => files.push(file),
+        }
+    }
+    let [input, output] = files[..] else {
+        return usage(Some("flip takes an input and an output file"));
+    };
+    let ext = Path::new(input).extension().unwrap_or_default();
+    if ![FlipFileType::Choreo, FlipFileType::Pathplanner]
+        .iter()
+        .any(|t| t.get_ext().as_str() == ext)
+    {
+        return usage(Some(&format!(
+            "{}: only .traj and .path files can be flipped here",
+            input
+        )));
+    }
+    if (opts.time_scale != 1.0 || opts.resample_dt.is_some())
+        && ext != FlipFileType::Choreo.get_ext().as_str()
+    {
+        return usage(Some(
+            "--time-scale and --resample only apply to .traj files",
+        ));
+    }
+
+    let (input, output) = (Path::new(input), Path::new(output));
+    // about the center of the venue the project is calibrated to
+    let center = match manifest::calibration(input) {
+        Ok(profile) => profile
+            .as_ref()
+            .map_or(flip::Field::NOMINAL, FieldProfile::field),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    }
+    .center();
+    for (option, value) in steps {
+        match transform_step(option, value, center) {
+            Some(step) => opts.custom_transform.push(step),
+            None => return usage(Some(&format!("bad value for {}", option))),
+        }
+    }
+    let mut plan = WritePlan::default();
+    let planned = manifest::plan_flip(input, output, &[], &opts, &mut plan)
+        .and_then(|()| manifest::record_flip(input, output, &[], &opts, &mut plan));
+    if let Err(err) = planned {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    write.apply(&plan)
+}
+
+// the value of a --translate, --rotate or --reflect option, turning or reflecting about
+// `center` unless it says where
+fn transform_step(option: &str, value: &str, center: [f64; 2]) -> Option<flip::TransformStep> {
+    let numbers = numbers(value)?;
+    let [cx, cy] = center;
+    match (option, numbers.as_slice()) {
+        ("--translate", &[x, y]) => Some(flip::TransformStep::Translate { x, y }),
+        ("--rotate", &[degrees]) => Some(flip::TransformStep::Rotate {
+            degrees,
+            x: cx,
+            y: cy,
+        }),
+        ("--rotate", &[degrees, x, y]) => Some(flip::TransformStep::Rotate { degrees, x, y }),
+        ("--reflect", &[degrees]) => Some(flip::TransformStep::Reflect {
+            degrees,
+            x: cx,
+            y: cy,
+        }),
+        ("--reflect", &[degrees, x, y]) => Some(flip::TransformStep::Reflect { degrees, x, y }),
+        _ => None,
+    }
+}
+
+fn numbers(value: &str) -> Option<Vec<f64>> {
+    value.split(',').map(|n| n.trim().parse().ok()).collect()
+}
+
+// the value of --precision: the rounding step for lengths, and for angles when it differs
+fn precision(value: &str) -> Option<flip::Precision> {
+    let (length, angle) = match numbers(value)?[..] {
+        [step] => (step, step),
+        [length, angle] => (length, angle),
+        _ => return None,
+    };
+    (length > 0.0 && angle > 0.0).then_some(flip::Precision { length, angle })
+}
+
+fn make_variants(args: &[String]) -> ExitCode {
+    let mut opts = flip::FlipOptions::default();
+    let mut write = WriteArgs::default();
+    let mut rules: Vec<(Variant, String)> = Vec::new();
+    let mut files = Vec::new();
+    for arg in args {
+        let (flag, rule) = arg.split_once('=').unwrap_or((arg, ""));
+        let variant = match flag {
+            "--same-alliance" => Variant::SameAlliance,
+            "--alliance" => Variant::Alliance,
+            "--both" => Variant::Both,
+            "--latest" => {
+                opts.write_latest = true;
+                continue;
+            }
+            "--mirror-headings" => {
+                opts.mirror_headings = true;
+                continue;
+            }
+            "--dry-run" => {
+                write.dry_run = true;
+                continue;
+            }
+            "--force" => {
+                write.force = true;
+                continue;
+            }
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            _ => {
+                files.push(arg.as_str());
+                continue;
+            }
+        };
+        let rule = if rule.is_empty() {
+            variant.default_rule()
+        } else {
+            rule
+        };
+        rules.retain(|(v, _)| *v != variant);
+        rules.push((variant, rule.to_owned()));
+    }
+    if rules.is_empty() {
+        rules = Variant::ALL
+            .into_iter()
+            .map(|v| (v, v.default_rule().to_owned()))
+            .collect();
+    }
+    let [input] = files[..] else {
+        return usage(Some("variants takes one input file"));
+    };
+
+    let input = Path::new(input);
+    let mut plan = WritePlan::default();
+    let planned = variants::auto_paths(input)
+        .and_then(|paths| variants::outputs(input, &paths, &rules, None))
+        .and_then(|flips| variants::plan_variants(input, &flips, &opts, &mut plan));
+    if let Err(err) = planned {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    write.apply(&plan)
+}
+
+fn regen(args: &[String]) -> ExitCode {
+    let mut write = WriteArgs::default();
+    let mut folders = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => write.dry_run = true,
+            "--force" => write.force = true,
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            folder => folders.push(folder),
+        }
+    }
+    let Some(manifest) = project_manifest(&folders) else {
+        return usage(Some("regen takes at most one folder"));
+    };
+    let mut plan = WritePlan::default();
+    match manifest::regenerate(&manifest, &mut plan) {
+        Ok(rebuilt) if rebuilt.is_empty() => {
+            println!("every flipped file is up to date");
+            ExitCode::SUCCESS
+        }
+        Ok(_) => write.apply(&plan),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn calibrate(args: &[String]) -> ExitCode {
+    let mut write = WriteArgs::default();
+    let mut nominal = false;
+    let mut precision = flip::Precision::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--nominal" => nominal = true,
+            "--precision" => match args.next().and_then(|v| self::precision(v)) {
+                Some(p) => precision = p,
+                None => return usage(Some("--precision takes positive steps in m and rad")),
+            },
+            "--dry-run" => write.dry_run = true,
+            "--force" => write.force = true,
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            file => files.push(file),
+        }
+    }
+    let (profile, folders) = match (nominal, files.split_first()) {
+        (true, _) => (None, &files[..]),
+        (false, Some((profile, folders))) => (Some(*profile), folders),
+        (false, None) => return usage(Some("calibrate takes a field profile or --nominal")),
+    };
+    let Some(manifest) = project_manifest(folders) else {
+        return usage(Some("calibrate takes at most one folder"));
+    };
+    let mut plan = WritePlan::default();
+    let planned = profile
+        .map(|p| FieldProfile::load(Path::new(p)))
+        .transpose()
+        .and_then(|profile| calibration::calibrate(&manifest, profile, &precision, &mut plan));
+    match planned {
+        Ok(_) if plan.writes.is_empty() => {
+            println!("the project is on that field already");
+            ExitCode::SUCCESS
+        }
+        Ok(_) => write.apply(&plan),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn watch(args: &[String]) -> ExitCode {
+    let mut folders = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            folder => folders.push(folder),
+        }
+    }
+    let Some(manifest) = project_manifest(&folders) else {
+        return usage(Some("watch takes at most one folder"));
+    };
+    let mut watcher = match Watcher::new(manifest) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("watching the sources in {}", watcher.manifest.display());
+    // the same error every poll is only worth printing once
+    let mut last_err = String::new();
+    loop {
+        match watcher.sync() {
+            Ok(Some(rebuilt)) => {
+                for output in rebuilt {
+                    println!("regenerated {}", output.display());
+                }
+                last_err.clear();
+            }
+            Ok(None) => {}
+            Err(err) if err.to_string() != last_err => {
+                last_err = err.to_string();
+                eprintln!("error: {}", last_err);
+            }
+            Err(_) => {}
+        }
+        std::thread::sleep(watch::POLL_INTERVAL);
+    }
+}
+
+fn check(args: &[String]) -> ExitCode {
+    let mut tolerance = check::DEFAULT_TOLERANCE;
+    let mut folders = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tolerance" => match args.next().and_then(|x| x.parse::<f64>().ok()) {
+                Some(x) if x >= 0.0 => tolerance = x,
+                _ => return usage(Some("--tolerance takes a number that isn't negative")),
+            },
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            folder => folders.push(folder),
+        }
+    }
+    let Some(manifest) = project_manifest(&folders) else {
+        return usage(Some("check takes at most one folder"));
+    };
+    match check::check(&manifest, tolerance) {
+        Ok(findings) if findings.is_empty() => {
+            println!("every flipped file matches its source");
+            ExitCode::SUCCESS
+        }
+        Ok(findings) => {
+            for finding in &findings {
+                eprintln!("{}", finding);
+            }
+            eprintln!("{} problem(s) with flipped files", findings.len());
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn compare(args: &[String]) -> ExitCode {
+    let (mut tolerance, mut heading_tolerance) = (0.01, 1.0);
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--tolerance" => &mut tolerance,
+            "--heading-tolerance" => &mut heading_tolerance,
+            flag if flag.starts_with("--") => {
+                return usage(Some(&format!("unknown option {}", flag)))
+            }
+            file => {
+                files.push(file);
+                continue;
+            }
+        };
+        match args.next().and_then(|x| x.parse::<f64>().ok()) {
+            Some(x) if x >= 0.0 => *value = x,
+            _ => return usage(Some(&format!("{} takes a number that isn't negative", arg))),
+        }
+    }
+    let [file, mirror] = files[..] else {
+        return usage(Some("compare takes a file and its mirror"));
+    };
+    let report = match symmetry::compare(Path::new(file), Path::new(mirror)) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    print!("{}", report);
+    let best = report.best();
+    let (wp_pos, wp_heading) = best.max_waypoint_error();
+    let (s_pos, s_heading) = best.max_sample_error();
+    if wp_pos.max(s_pos) <= tolerance && wp_heading.max(s_heading).to_degrees() <= heading_tolerance
+    {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!(
+            "error: {} isn't a mirror of {} within tolerance",
+            mirror, file
+        );
+        ExitCode::FAILURE
+    }
+}
+
+// the manifest of the project the folder argument is in, None for more than one folder
+fn project_manifest(folders: &[&str]) -> Option<PathBuf> {
+    let folder = match folders {
+        [] => ".",
+        [folder] => folder,
+        _ => return None,
+    };
+    Some(manifest::manifest_path(
+        &Path::new(folder).join(MANIFEST_NAME),
+    ))
+}
+
+#[derive(Default)]
+struct WriteArgs {
+    dry_run: bool,
+    force: bool,
+}
+
+impl WriteArgs {
+    // lists the plan, then writes it unless this is a dry run or it overwrites files
+    // without --force
+    fn apply(&self, plan: &WritePlan) -> ExitCode {
+        print!("{}", plan);
+        if self.dry_run {
+            return ExitCode::SUCCESS;
+        }
+        if plan.overwrites() > 0 && !self.force {
+            eprintln!("error: nothing written, pass --force to overwrite existing files");
+            return ExitCode::FAILURE;
+        }
+        match plan.commit() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+fn usage(problem: Option<&str>) -> ExitCode {
+    if let Some(problem) = problem {
+        eprintln!("error: {}\n", problem);
+    }
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
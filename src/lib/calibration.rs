@@ -0,0 +1,464 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::lib::{
+    chor::ChoreoData,
+    error::{Error, Result},
+    flip::{self, AxisMap, Field, Flippable, Precision, Transform2d},
+    format,
+    manifest::{self, Manifest},
+    migrate::{self, Versioned},
+    pathplanner::{auto::AutoData, path::PathData},
+    plan::WritePlan,
+    project, util,
+};
+
+// real fields are a few centimetres off the drawings, so teams measure them at each event.
+// A profile holds those measurements, and calibrating a project moves every path,
+// trajectory and auto from the field they're on onto the profile's. The manifest keeps the
+// profile, so flips from then on are made about the venue's own center lines. Choreo
+// project variables stay nominal: waypoints using them get the calibration written into
+// their expressions
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldProfile {
+    pub name: String,
+    // measured, in meters
+    pub length: f64,
+    pub width: f64,
+    // where the nominal field's origin was measured to be
+    #[serde(default)]
+    pub origin: [f64; 2],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<ElementOffset>,
+}
+
+// a field element that sits apart from where rescaling the field puts it, like a reef
+// bolted down a little off. Waypoints and point towards targets within `radius` of it move
+// with it
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementOffset {
+    pub name: String,
+    // on the nominal field
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+    // how far from its rescaled position it was measured
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl FieldProfile {
+    pub fn load(path: &Path) -> Result<FieldProfile> {
+        let text = WritePlan::default().read(path)?;
+        let profile: FieldProfile =
+            serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+                .map_err(|e| Error::parse(path, e))?;
+        if profile.length <= 0.0 || profile.width <= 0.0 {
+            return Err(Error::schema(
+                path,
+                "the field's length and width have to be positive",
+            ));
+        }
+        if let Some(e) = profile.elements.iter().find(|e| e.radius < 0.0) {
+            return Err(Error::schema(
+                path,
+                format!("the radius of {} can't be negative", e.name),
+            ));
+        }
+        Ok(profile)
+    }
+
+    pub fn field(&self) -> Field {
+        Field {
+            length: self.length,
+            width: self.width,
+            origin: self.origin,
+        }
+    }
+
+    // from the nominal field onto this one, elements aside
+    pub fn to_measured(&self) -> Transform2d {
+        Transform2d::stretch(self.length / flip::FIELD_X, self.width / flip::FIELD_Y)
+            .then(Transform2d::translate(self.origin[0], self.origin[1]))
+    }
+
+    pub fn to_nominal(&self) -> Transform2d {
+        Transform2d::translate(-self.origin[0], -self.origin[1]).then(Transform2d::stretch(
+            flip::FIELD_X / self.length,
+            flip::FIELD_Y / self.width,
+        ))
+    }
+
+    pub fn add_offsets(&self, data: &mut impl Waypoints) {
+        data.shift_waypoints(&|p| self.element_shift(p, false));
+    }
+
+    pub fn remove_offsets(&self, data: &mut impl Waypoints) {
+        data.shift_waypoints(&|p| self.element_shift(p, true));
+    }
+
+    // how far the element around `p` moves it, or back when the offsets are `applied`
+    // already
+    fn element_shift(&self, p: [f64; 2], applied: bool) -> [f64; 2] {
+        let to_measured = self.to_measured();
+        for e in &self.elements {
+            let [mut x, mut y] = to_measured.point([e.x, e.y]);
+            if applied {
+                x += e.dx;
+                y += e.dy;
+            }
+            if (p[0] - x).hypot(p[1] - y) <= e.radius {
+                return if applied {
+                    [-e.dx, -e.dy]
+                } else {
+                    [e.dx, e.dy]
+                };
+            }
+        }
+        [0.0, 0.0]
+    }
+}
+
+// runs `flip` on data that's on `profile`'s field, taking the element offsets off first
+// and adding them back where the waypoints land, so they follow the element there
+pub fn flip_on_venue<T: Waypoints>(
+    data: &mut T,
+    profile: Option<&FieldProfile>,
+    flip: impl FnOnce(&mut T),
+) {
+    if let Some(profile) = profile {
+        profile.remove_offsets(data);
+    }
+    flip(data);
+    if let Some(profile) = profile {
+        profile.add_offsets(data);
+    }
+}
+
+// what element offsets move: the waypoints a path, trajectory or auto is made from, and
+// the positions a path points towards. `shift` gives how far a position moves
+pub trait Waypoints {
+    fn shift_waypoints(&mut self, shift: &dyn Fn([f64; 2]) -> [f64; 2]);
+}
+
+impl Waypoints for PathData {
+    fn shift_waypoints(&mut self, shift: &dyn Fn([f64; 2]) -> [f64; 2]) {
+        for wp in &mut self.waypoints {
+            // the control points keep their place around the anchor
+            let [dx, dy] = shift([wp.anchor.x, wp.anchor.y]);
+            let controls = [wp.prev_control.as_mut(), wp.next_control.as_mut()];
+            for point in std::iter::once(&mut wp.anchor).chain(controls.into_iter().flatten()) {
+                point.x += dx;
+                point.y += dy;
+            }
+        }
+        for zone in &mut self.point_towards_zones {
+            let [dx, dy] = shift([zone.field_position.x, zone.field_position.y]);
+            zone.field_position.x += dx;
+            zone.field_position.y += dy;
+        }
+    }
+}
+
+impl Waypoints for ChoreoData {
+    // the samples are left to choreo, which regenerates them from the moved waypoints
+    fn shift_waypoints(&mut self, shift: &dyn Fn([f64; 2]) -> [f64; 2]) {
+        for wp in &mut self.params.waypoints {
+            let [dx, dy] = shift([wp.x.val, wp.y.val]);
+            wp.x.map(
+                AxisMap {
+                    offset: dx,
+                    scale: 1.0,
+                },
+                "m",
+            );
+            wp.y.map(
+                AxisMap {
+                    offset: dy,
+                    scale: 1.0,
+                },
+                "m",
+            );
+        }
+        for wp in &mut self.snapshot.waypoints {
+            let [dx, dy] = shift([wp.x, wp.y]);
+            wp.x += dx;
+            wp.y += dy;
+        }
+    }
+}
+
+impl Waypoints for AutoData {
+    fn shift_waypoints(&mut self, shift: &dyn Fn([f64; 2]) -> [f64; 2]) {
+        if let Some(pose) = self.starting_pose.as_mut() {
+            let [dx, dy] = shift([pose.position.x, pose.position.y]);
+            pose.position.x += dx;
+            pose.position.y += dy;
+        }
+    }
+}
+
+// moves every path, trajectory and auto in the project of the manifest at `manifest_path`
+// from the field it's on now onto `profile`'s, or back to the nominal field for None.
+// Flipped files in the manifest are flipped again from their moved sources instead.
+// Moved files are written rounded to `precision`. Returns the files moved
+pub fn calibrate(
+    manifest_path: &Path,
+    profile: Option<FieldProfile>,
+    precision: &Precision,
+    plan: &mut WritePlan,
+) -> Result<Vec<PathBuf>> {
+    let root = manifest_path.parent().unwrap_or(Path::new(""));
+    let mut manifest = Manifest::load(manifest_path, plan)?;
+    let from = manifest.field.take();
+    if from == profile {
+        return Ok(Vec::new());
+    }
+    let outputs: HashSet<PathBuf> = manifest
+        .outputs(root)
+        .iter()
+        .map(|o| project::absolute(o))
+        .collect();
+    let transform = from
+        .as_ref()
+        .map_or(Transform2d::IDENTITY, FieldProfile::to_nominal)
+        .then(
+            profile
+                .as_ref()
+                .map_or(Transform2d::IDENTITY, FieldProfile::to_measured),
+        );
+
+    let mut moved = Vec::new();
+    let files: Vec<PathBuf> = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e == "path" || e == "traj" || e == "auto")
+                && !outputs.contains(&project::absolute(p))
+        })
+        .collect();
+    for file in files {
+        let fields = Fields {
+            from: from.as_ref(),
+            transform: &transform,
+            to: profile.as_ref(),
+            precision,
+        };
+        let changed = match file.extension().and_then(|e| e.to_str()) {
+            Some("traj") => move_file::<ChoreoData>(&file, &fields, plan)?,
+            Some("path") => move_file::<PathData>(&file, &fields, plan)?,
+            _ => move_file::<AutoData>(&file, &fields, plan)?,
+        };
+        if changed {
+            moved.push(file);
+        }
+    }
+
+    manifest.field = profile;
+    manifest.save(manifest_path, plan)?;
+    if !manifest.entries.is_empty() {
+        manifest::regenerate(manifest_path, plan)?;
+    }
+    Ok(moved)
+}
+
+// what calibrating moves in each kind of file
+pub trait Calibrate: Versioned + Waypoints {
+    fn recalibrate(&mut self, transform: &Transform2d);
+}
+
+impl Calibrate for PathData {
+    fn recalibrate(&mut self, transform: &Transform2d) {
+        // moved, the waypoints are still the same linked waypoints
+        let names: Vec<Option<String>> = self
+            .waypoints
+            .iter()
+            .map(|w| w.linked_name.clone())
+            .collect();
+        self.transform(transform);
+        for (wp, name) in self.waypoints.iter_mut().zip(names) {
+            wp.linked_name = name;
+        }
+    }
+}
+
+impl Calibrate for ChoreoData {
+    fn recalibrate(&mut self, transform: &Transform2d) {
+        self.transform(transform);
+    }
+}
+
+impl Calibrate for AutoData {
+    fn recalibrate(&mut self, transform: &Transform2d) {
+        if let Some(pose) = self.starting_pose.as_mut() {
+            pose.transform(transform);
+        }
+    }
+}
+
+// a move from one field onto another
+struct Fields<'a> {
+    from: Option<&'a FieldProfile>,
+    transform: &'a Transform2d,
+    to: Option<&'a FieldProfile>,
+    precision: &'a Precision,
+}
+
+// plans `file` moved, and leaves it alone, formatting and all, when nothing in it moves.
+// Returns whether it moved
+fn move_file<T: Calibrate>(file: &Path, fields: &Fields, plan: &mut WritePlan) -> Result<bool> {
+    let mut loaded = migrate::load_planned::<T>(file, plan)?;
+    let before = loaded.to_value(false);
+    if let Some(from) = fields.from {
+        from.remove_offsets(&mut loaded.data);
+    }
+    loaded.data.recalibrate(fields.transform);
+    if let Some(to) = fields.to {
+        to.add_offsets(&mut loaded.data);
+    }
+    let mut json = loaded.to_value(false);
+    if json == before {
+        return Ok(false);
+    }
+    let is_choreo = file.extension().is_some_and(|e| e == "traj");
+    util::normalize_json(&mut json, fields.precision, !is_choreo);
+    let text = if is_choreo {
+        format::choreo_string(&json)
+    } else {
+        format::pathplanner_string(&json)
+    };
+    plan.modify(file, text)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{
+        fixtures::{self, path_json},
+        flip::FlipOptions,
+    };
+
+    // a little longer, narrower and further along than the nominal field, with the blue
+    // reef measured off its rescaled position and the red one where it should be
+    fn profile() -> FieldProfile {
+        let reef = |name: &str, x: f64, dx: f64| ElementOffset {
+            name: name.to_owned(),
+            x,
+            y: 4.0,
+            radius: 0.5,
+            dx,
+            dy: -0.05,
+        };
+        FieldProfile {
+            name: "venue".to_owned(),
+            length: flip::FIELD_X + 0.2,
+            width: flip::FIELD_Y - 0.07,
+            origin: [0.05, 0.0],
+            elements: vec![
+                reef("blue reef", 4.0, 0.1),
+                reef("red reef", flip::FIELD_X - 4.0, 0.0),
+            ],
+        }
+    }
+
+    fn anchors(data: &PathData) -> Vec<[f64; 2]> {
+        data.waypoints
+            .iter()
+            .map(|w| [w.anchor.x, w.anchor.y])
+            .collect()
+    }
+
+    fn close(a: [f64; 2], b: [f64; 2], tolerance: f64) -> bool {
+        (a[0] - b[0]).abs() < tolerance && (a[1] - b[1]).abs() < tolerance
+    }
+
+    #[test]
+    fn flips_are_made_about_the_measured_field() {
+        let profile = profile();
+        let to_measured = profile.to_measured();
+        assert!(close(
+            to_measured.point([flip::FIELD_X, flip::FIELD_Y]),
+            [0.05 + profile.length, profile.width],
+            1e-9
+        ));
+        let back = to_measured.then(profile.to_nominal());
+        assert!(close(back.point([3.0, 2.0]), [3.0, 2.0], 1e-9));
+
+        let opts = FlipOptions {
+            same_alliance: false,
+            calibration: Some(profile.clone()),
+            ..Default::default()
+        };
+        let center = 0.05 + profile.length / 2.0;
+        assert!(close(
+            opts.transform().point([1.0, 2.0]),
+            [2.0 * center - 1.0, 2.0],
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn element_offsets_follow_the_waypoints_near_them() {
+        let profile = profile();
+        let to_measured = profile.to_measured();
+        let blue = to_measured.point([4.0, 4.0]);
+        let red = to_measured.point([flip::FIELD_X - 4.0, 4.0]);
+        // at the blue reef where it was measured, and far from either reef
+        let near = [blue[0] + 0.1, blue[1] - 0.05];
+        let far = [8.0, 1.0];
+        let mut data: PathData = serde_json::from_value(path_json(&[near, far])).unwrap();
+        let alliance = Transform2d::alliance(&profile.field());
+        flip_on_venue(&mut data, Some(&profile), |data| data.transform(&alliance));
+        let [flipped_near, flipped_far] = anchors(&data)[..] else {
+            panic!("two waypoints");
+        };
+        // onto the red reef, with its own offset instead of the blue one
+        assert!(close(flipped_near, [red[0], red[1] - 0.05], 1e-9));
+        assert!(close(flipped_far, alliance.point(far), 1e-9));
+    }
+
+    #[test]
+    fn calibrating_moves_only_what_moves() {
+        let deploy = fixtures::project("calibrate");
+        let path = fixtures::write_path(&deploy, "A", &[[4.0, 4.0], [8.0, 1.0]]);
+        // nothing in an auto without a starting pose is on the field
+        let auto = fixtures::write_auto(&deploy, "A", &["A"]);
+
+        let profile = profile();
+        let precision = Precision {
+            length: 1e-3,
+            angle: 1e-3,
+        };
+        let manifest_path = deploy.join(manifest::MANIFEST_NAME);
+        let mut plan = WritePlan::default();
+        let moved =
+            calibrate(&manifest_path, Some(profile.clone()), &precision, &mut plan).unwrap();
+        assert_eq!(moved, vec![path.clone()]);
+        assert!(plan.writes.iter().all(|w| w.path != auto));
+
+        let written: PathData = serde_json::from_str(&plan.read(&path).unwrap()).unwrap();
+        let [reef, far] = anchors(&written)[..] else {
+            panic!("two waypoints");
+        };
+        let to_measured = profile.to_measured();
+        let blue = to_measured.point([4.0, 4.0]);
+        assert!(close(reef, [blue[0] + 0.1, blue[1] - 0.05], 5e-4));
+        assert!(close(far, to_measured.point([8.0, 1.0]), 5e-4));
+        // rounded to the millimetre asked for
+        for v in [reef, far].concat() {
+            assert!((v * 1e3 - (v * 1e3).round()).abs() < 1e-6, "{}", v);
+        }
+        fixtures::remove(&deploy);
+    }
+}
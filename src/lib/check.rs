@@ -0,0 +1,383 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::lib::{
+    error::{Error, Result},
+    flip::FlipOptions,
+    manifest::{self, Manifest},
+    plan::WritePlan,
+    project,
+};
+
+// checks a project against its manifest: every registered output has to be what flipping
+// its source gives today, and flipped files the manifest doesn't know about are reported
+
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+// differences listed per file before the rest are only counted
+const MAX_DIFFS: usize = 8;
+
+pub enum Finding {
+    Missing {
+        output: PathBuf,
+        source: PathBuf,
+    },
+    Stale {
+        output: PathBuf,
+        source: PathBuf,
+        diffs: Vec<String>,
+    },
+    // a flipped file no manifest entry produces, with the file it looks flipped from when
+    // that could be found. When that isn't in the manifest either, which of the two was
+    // flipped from the other can't be told, and they're reported together as a `pair`
+    Unregistered {
+        path: PathBuf,
+        source: Option<PathBuf>,
+        pair: bool,
+    },
+}
+
+pub fn check(manifest_path: &Path, tolerance: f64) -> Result<Vec<Finding>> {
+    manifest::check_exists(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or(Path::new(""));
+    let manifest = Manifest::load(manifest_path, &WritePlan::default())?;
+    let mut findings = Vec::new();
+    let mut registered = HashSet::new();
+
+    for entry in &manifest.entries {
+        let source = root.join(&entry.source);
+        let paths: Vec<(PathBuf, PathBuf)> = entry
+            .paths
+            .iter()
+            .map(|p| (root.join(&p.source), root.join(&p.output)))
+            .collect();
+        let output = root.join(&entry.output);
+        let mut plan = WritePlan::default();
+        manifest::plan_flip(&source, &output, &paths, &entry.options(), &mut plan)?;
+
+        let pairs = std::iter::once((source.clone(), output)).chain(paths);
+        for (source, output) in pairs {
+            registered.insert(project::absolute(&source));
+            registered.insert(project::absolute(&output));
+            let Some(expected) = planned_json(&plan, &output) else {
+                continue;
+            };
+            if !output.exists() {
+                findings.push(Finding::Missing { output, source });
+                continue;
+            }
+            let actual = read_json(&output)?;
+            let mut diffs = Vec::new();
+            diff(
+                &expected,
+                &actual,
+                tolerance,
+                &mut String::new(),
+                &mut diffs,
+            );
+            if !diffs.is_empty() {
+                findings.push(Finding::Stale {
+                    output,
+                    source,
+                    diffs,
+                });
+            }
+        }
+    }
+
+    findings.extend(unregistered(root, &registered, tolerance));
+    Ok(findings)
+}
+
+fn planned_json(plan: &WritePlan, path: &Path) -> Option<Value> {
+    let write = plan.writes.iter().find(|w| w.path == path)?;
+    serde_json::from_str(&write.contents).ok()
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let text = WritePlan::default().read(path)?;
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+        .map_err(|e| Error::parse(path, e))
+}
+
+// files under `root` that look flipped but aren't in the manifest: pathplanner files saved
+// in the "Flipped" folder, and paths or trajectories whose waypoints are the flip of
+// another file's
+fn unregistered(root: &Path, registered: &HashSet<PathBuf>, tolerance: f64) -> Vec<Finding> {
+    let files: Vec<PathBuf> = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e == "path" || e == "traj" || e == "auto")
+        })
+        .collect();
+    let jsons: Vec<Option<Value>> = files.iter().map(|f| read_json(f).ok()).collect();
+    // every path and trajectory flipped both ways, compared by waypoints
+    let mut flips = Vec::new();
+    for file in files
+        .iter()
+        .filter(|f| f.extension().is_some_and(|e| e != "auto"))
+    {
+        for same_alliance in [true, false] {
+            let opts = FlipOptions {
+                same_alliance,
+                mirror_variables: false,
+                ..Default::default()
+            };
+            let mut plan = WritePlan::default();
+            let output = file.with_file_name(".pathflip-check");
+            if manifest::plan_flip(file, &output, &[], &opts, &mut plan).is_ok() {
+                if let Some(json) = planned_json(&plan, &output) {
+                    flips.push((file, json));
+                }
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (file, json) in files.iter().zip(&jsons) {
+        let Some(json) = json else {
+            continue;
+        };
+        // reported with the pair it's in already
+        let in_pair = findings.iter().any(
+            |f| matches!(f, Finding::Unregistered { source: Some(s), pair: true, .. } if s == file),
+        );
+        if registered.contains(&project::absolute(file)) || in_pair {
+            continue;
+        }
+        let source = waypoints(json).and_then(|wp| {
+            flips.iter().find_map(|(source, flipped)| {
+                let mut diffs = Vec::new();
+                let other = waypoints(flipped)?;
+                diff(other, wp, tolerance, &mut String::new(), &mut diffs);
+                (*source != file && diffs.is_empty()).then(|| (*source).clone())
+            })
+        });
+        let in_flipped_folder = json.get("folder").and_then(Value::as_str) == Some("Flipped");
+        if source.is_some() || in_flipped_folder {
+            let pair = source
+                .as_ref()
+                .is_some_and(|s| !registered.contains(&project::absolute(s)));
+            findings.push(Finding::Unregistered {
+                path: file.clone(),
+                source,
+                pair,
+            });
+        }
+    }
+    findings
+}
+
+// the waypoints of a pathplanner path or choreo trajectory
+fn waypoints(json: &Value) -> Option<&Value> {
+    json.pointer("/snapshot/waypoints")
+        .or_else(|| json.pointer("/waypoints"))
+        .filter(|wp| wp.as_array().is_some_and(|a| !a.is_empty()))
+}
+
+// differences from `expected` to `actual` as "pointer: expected x, found y", numbers within
+// `tolerance` of each other being equal
+fn diff(
+    expected: &Value,
+    actual: &Value,
+    tolerance: f64,
+    pointer: &mut String,
+    out: &mut Vec<String>,
+) {
+    let len = pointer.len();
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => {
+            let (e, a) = (
+                e.as_f64().unwrap_or_default(),
+                a.as_f64().unwrap_or_default(),
+            );
+            if (e - a).abs() > tolerance {
+                out.push(format!(
+                    "{}: expected {}, found {}",
+                    pointer_or_root(pointer),
+                    e,
+                    a
+                ));
+            }
+        }
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, ev) in e {
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                match a.get(key) {
+                    Some(av) => diff(ev, av, tolerance, pointer, out),
+                    None => out.push(format!("{}: missing", pointer)),
+                }
+                pointer.truncate(len);
+            }
+            for key in a.keys().filter(|k| !e.contains_key(*k)) {
+                out.push(format!("{}/{}: not expected", pointer, key));
+            }
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => {
+            for (i, (ev, av)) in e.iter().zip(a).enumerate() {
+                pointer.push_str(&format!("/{}", i));
+                diff(ev, av, tolerance, pointer, out);
+                pointer.truncate(len);
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => out.push(format!(
+            "{}: expected {} items, found {}",
+            pointer_or_root(pointer),
+            e.len(),
+            a.len()
+        )),
+        (e, a) if e != a => out.push(format!(
+            "{}: expected {}, found {}",
+            pointer_or_root(pointer),
+            e,
+            a
+        )),
+        _ => {}
+    }
+}
+
+fn pointer_or_root(pointer: &str) -> &str {
+    if pointer.is_empty() {
+        "/"
+    } else {
+        pointer
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Missing { output, source } => write!(
+                f,
+                "missing: {} (flipped from {})",
+                output.display(),
+                source.display()
+            ),
+            Finding::Stale {
+                output,
+                source,
+                diffs,
+            } => {
+                write!(
+                    f,
+                    "stale: {} doesn't match the flip of {}",
+                    output.display(),
+                    source.display()
+                )?;
+                for d in diffs.iter().take(MAX_DIFFS) {
+                    write!(f, "\n  {}", d)?;
+                }
+                if diffs.len() > MAX_DIFFS {
+                    write!(f, "\n  ...and {} more", diffs.len() - MAX_DIFFS)?;
+                }
+                Ok(())
+            }
+            Finding::Unregistered {
+                path,
+                source: Some(source),
+                pair: true,
+            } => write!(
+                f,
+                "unregistered: {} and {} are flips of each other, but neither is in the manifest",
+                path.display(),
+                source.display()
+            ),
+            Finding::Unregistered { path, source, .. } => {
+                write!(f, "unregistered: {} looks flipped", path.display())?;
+                if let Some(source) = source {
+                    write!(f, " from {}", source.display())?;
+                }
+                write!(f, " but isn't in the manifest")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::ExitCode};
+
+    use super::*;
+    use crate::lib::fixtures::{self, project, write_path};
+
+    fn flip(source: &Path, output: &Path, record: bool) {
+        let mut plan = WritePlan::default();
+        let opts = FlipOptions::default();
+        manifest::plan_flip(source, output, &[], &opts, &mut plan).unwrap();
+        if record {
+            manifest::record_flip(source, output, &[], &opts, &mut plan).unwrap();
+        }
+        plan.commit().unwrap();
+    }
+
+    #[test]
+    fn edited_outputs_are_stale() {
+        let deploy = project("check-stale");
+        let source = write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        let output = source.with_file_name("A_Mirrored.path");
+        flip(&source, &output, true);
+        let manifest = deploy.join(manifest::MANIFEST_NAME);
+        assert!(check(&manifest, DEFAULT_TOLERANCE).unwrap().is_empty());
+
+        let mut json = read_json(&output).unwrap();
+        json["waypoints"][0]["anchor"]["x"] = 1.5.into();
+        fs::write(&output, json.to_string()).unwrap();
+        let findings = check(&manifest, DEFAULT_TOLERANCE).unwrap();
+        let [Finding::Stale { diffs, .. }] = &findings[..] else {
+            panic!("one stale output");
+        };
+        assert_eq!(diffs, &["/waypoints/0/anchor/x: expected 1, found 1.5"]);
+        let report = findings[0].to_string();
+        assert!(report.starts_with("stale: "), "{}", report);
+        assert!(report.ends_with("\n  /waypoints/0/anchor/x: expected 1, found 1.5"));
+
+        let args = ["check".to_owned(), deploy.display().to_string()];
+        assert_eq!(crate::cli::run(&args), ExitCode::FAILURE);
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn mirrors_made_without_the_manifest_are_found() {
+        let deploy = project("check-unregistered");
+        let a = write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        flip(&a, &a.with_file_name("A_Mirrored.path"), true);
+        // a registered source flipped by hand, and a pair of which neither is registered
+        let by_hand = a.with_file_name("A_Copy.path");
+        flip(&a, &by_hand, false);
+        let b = write_path(&deploy, "B", &[[2.0, 2.0], [5.0, 1.0]]);
+        let b_flipped = b.with_file_name("B_Flipped.path");
+        flip(&b, &b_flipped, false);
+
+        let manifest = deploy.join(manifest::MANIFEST_NAME);
+        let findings = check(&manifest, DEFAULT_TOLERANCE).unwrap();
+        let found: Vec<(&Path, Option<&Path>, bool)> = findings
+            .iter()
+            .map(|f| match f {
+                Finding::Unregistered { path, source, pair } => {
+                    (path.as_path(), source.as_deref(), *pair)
+                }
+                _ => panic!("only unregistered files: {}", f),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (by_hand.as_path(), Some(a.as_path()), false),
+                (b.as_path(), Some(b_flipped.as_path()), true),
+            ]
+        );
+        assert!(findings[1].to_string().contains("are flips of each other"));
+        fixtures::remove(&deploy);
+    }
+}
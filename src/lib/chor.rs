@@ -1,4 +1,18 @@
-use crate::{flip::Flippable, lib::flip};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    flip::{AxisMap, Flippable, Transform2d},
+    lib::{
+        error, expr,
+        pathplanner::{auto, commands::PPCommand},
+        plan::WritePlan,
+        project,
+        util::{self, Pose2d},
+    },
+};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct ChoreoData {
@@ -7,24 +21,250 @@ pub struct ChoreoData {
     pub snapshot: ChoreoSnapshotData,
     pub params: ChoreoParams,
     pub trajectory: ChoreoTraj,
-    pub events: serde_json::Value,
+    pub events: Vec<ChoreoEvent>,
 }
 
 impl Flippable for ChoreoData {
-    fn flip_alliance(&mut self) {
-        self.snapshot.flip_alliance();
-        self.params.flip_alliance();
-        self.trajectory.flip_alliance();
+    fn transform(&mut self, t: &Transform2d) {
+        self.snapshot.transform(t);
+        self.params.transform(t);
+        self.trajectory.transform(t);
+        // events are anchored in time, nothing in them is on the field
+    }
+}
+
+impl ChoreoData {
+    // transforms like Flippable::transform, then points waypoints that reference project
+    // variables at mirrored copies of those variables, which are added to the project.
+    // Variables can only follow a transform that maps x, y and heading each on their own,
+    // under any other the waypoints are left as plain values
+    pub fn flip_with_project(&mut self, t: &Transform2d, project: &mut ChoreoProject) {
+        let original: Vec<ChoreoWaypoint> = self.params.waypoints.clone();
+        self.transform(t);
+        let Some(maps) = t.axis_maps() else {
+            return;
+        };
+
+        let vars = project.variables();
+        let mut mirror = VariableMirror {
+            scope: vars.scope(),
+            vars,
+            maps,
+            poses: HashMap::new(),
+            expressions: HashMap::new(),
+            new_vars: Vec::new(),
+        };
+        // mapped axes first so every mirrored pose is known before following them
+        let (mapped, unchanged): (Vec<WaypointAxis>, Vec<WaypointAxis>) = WaypointAxis::ALL
+            .into_iter()
+            .partition(|a| a.map(&maps).is_some());
+        for axis in mapped {
+            for (wp, orig) in self.params.waypoints.iter_mut().zip(original.iter()) {
+                if let Some(exp) = mirror.map(&orig.get(axis).exp, axis) {
+                    wp.get_mut(axis).exp = exp;
+                }
+            }
+        }
+        for axis in unchanged {
+            for wp in self.params.waypoints.iter_mut() {
+                if let Some(exp) = mirror.follow(&wp.get(axis).exp, axis) {
+                    wp.get_mut(axis).exp = exp;
+                }
+            }
+        }
+        for (table, name, value) in mirror.new_vars {
+            project.insert_variable(table, &name, value);
+        }
     }
 
-    fn flip_same_alliance(&mut self) {
-        self.snapshot.flip_same_alliance();
-        self.params.flip_same_alliance();
-        self.trajectory.flip_same_alliance();
+    // waypoint expressions that no longer evaluate to their stored value, e.g. after a
+    // project variable was edited without regenerating
+    pub fn expression_errors(&self, scope: &expr::Scope) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (i, wp) in self.params.waypoints.iter().enumerate() {
+            for (field, value) in [("x", &wp.x), ("y", &wp.y), ("heading", &wp.heading)] {
+                if let Err(e) = value.check(scope) {
+                    errors.push(format!("waypoint {} {}: {}", i, field, e));
+                }
+            }
+        }
+        errors
+    }
+
+    // reasons choreo would regenerate this trajectory differently: the editor params no
+    // longer match the snapshot the samples came from, or the samples don't start and
+    // end on the snapshot waypoints
+    pub fn stale_reasons(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+        let params = &self.params;
+        let snapshot = &self.snapshot;
+        if params.waypoints.len() != snapshot.waypoints.len() {
+            reasons.push(format!(
+                "{} waypoints in params but {} in snapshot",
+                params.waypoints.len(),
+                snapshot.waypoints.len()
+            ));
+        }
+        for (i, (p, s)) in params
+            .waypoints
+            .iter()
+            .zip(snapshot.waypoints.iter())
+            .enumerate()
+        {
+            let mut fields = Vec::new();
+            if (p.x.val - s.x).abs() > STALE_TOL {
+                fields.push("x");
+            }
+            if (p.y.val - s.y).abs() > STALE_TOL {
+                fields.push("y");
+            }
+            if (p.heading.val - s.heading).abs() > STALE_TOL {
+                fields.push("heading");
+            }
+            if p.intervals != s.intervals && p.override_intervals {
+                fields.push("intervals");
+            }
+            if p.split != s.split {
+                fields.push("split");
+            }
+            if p.fix_translation != s.fix_translation || p.fix_heading != s.fix_heading {
+                fields.push("fixed");
+            }
+            if p.override_intervals != s.override_intervals {
+                fields.push("overrideIntervals");
+            }
+            if !fields.is_empty() {
+                reasons.push(format!(
+                    "waypoint {} changed since generation ({})",
+                    i + 1,
+                    fields.join(", ")
+                ));
+            }
+        }
+        if (params.target_dt.val - snapshot.target_dt).abs() > STALE_TOL {
+            reasons.push(format!(
+                "targetDt is {} but trajectory was generated with {}",
+                params.target_dt.val, snapshot.target_dt
+            ));
+        }
+        let constraints = |cs: &Vec<ChoreoConstraint>| -> Vec<serde_json::Value> {
+            cs.iter()
+                .filter_map(|c| serde_json::to_value(c).ok())
+                .map(|v| strip_expressions(&v))
+                .collect()
+        };
+        if constraints(&params.constraints) != constraints(&snapshot.constraints) {
+            reasons.push("constraints changed since generation".to_owned());
+        }
+        match (
+            self.trajectory.start_pose(),
+            self.trajectory.end_pose(),
+            snapshot.waypoints.first(),
+            snapshot.waypoints.last(),
+        ) {
+            (Some(start), Some(end), Some(first), Some(last)) => {
+                for (name, pose, wp) in [("start", start, first), ("end", end, last)] {
+                    let off = (pose.x - wp.x).hypot(pose.y - wp.y);
+                    let turn = util::wrap_angle(pose.heading - wp.heading).abs();
+                    if (wp.fix_translation && off > STALE_POS_TOL_M)
+                        || (wp.fix_heading && turn > STALE_HEADING_TOL_RAD)
+                    {
+                        reasons.push(format!(
+                            "samples {} {:.3} m / {:.1}\u{b0} away from the snapshot waypoint",
+                            name,
+                            off,
+                            turn.to_degrees()
+                        ));
+                    }
+                }
+            }
+            (None, ..) => reasons.push("trajectory has no samples".to_owned()),
+            _ => {}
+        }
+        reasons
+    }
+
+    pub fn time_scale(&mut self, factor: f64) {
+        self.trajectory.time_scale(factor);
+        for event in self.events.iter_mut() {
+            if let Some(t) = event.from.target_timestamp.as_mut() {
+                *t *= factor;
+            }
+            event.from.offset.map(
+                AxisMap {
+                    offset: 0.0,
+                    scale: factor,
+                },
+                "s",
+            );
+        }
+    }
+
+    pub fn resample(&mut self, dt: f64) {
+        let intervals = self.trajectory.resample(dt);
+        self.snapshot.target_dt = dt;
+        self.params.target_dt.val = dt;
+        self.params.target_dt.update_exp("s");
+        for (i, count) in intervals.into_iter().enumerate() {
+            if let Some(wp) = self.snapshot.waypoints.get_mut(i) {
+                wp.intervals = count;
+            }
+            if let Some(wp) = self.params.waypoints.get_mut(i) {
+                wp.intervals = count;
+            }
+        }
+    }
+
+    // the same trajectory run from its end to its start. Waypoints, constraints and events
+    // are turned around with the samples, so choreo regenerates it the same way
+    pub fn reverse(&mut self) {
+        let duration = self.trajectory.duration();
+        let last = self.params.waypoints.len().saturating_sub(1);
+        self.trajectory.reverse();
+        reverse_waypoints(&mut self.snapshot.waypoints);
+        reverse_waypoints(&mut self.params.waypoints);
+        reverse_constraints(&mut self.snapshot.constraints, last);
+        reverse_constraints(&mut self.params.constraints, last);
+        self.events.reverse();
+        for event in self.events.iter_mut() {
+            // at `duration - (anchor + offset)`, so as far before its anchor as it was after
+            event.from.target = event.from.target.as_ref().map(|t| t.reversed(last));
+            if let Some(t) = event.from.target_timestamp.as_mut() {
+                *t = duration - *t;
+            }
+            event.from.offset.map(
+                AxisMap {
+                    offset: 0.0,
+                    scale: -1.0,
+                },
+                "s",
+            );
+        }
+    }
+
+    // absolute time of an event in seconds, resolving waypoint anchored events through
+    // the waypoint timestamps of the generated trajectory
+    pub fn event_timestamp(&self, event: &ChoreoEvent) -> Option<f64> {
+        let anchor_t = match (&event.from.target_timestamp, &event.from.target) {
+            (Some(t), _) => *t,
+            (None, Some(target)) => {
+                let idx = match target {
+                    ChoreoWaypointName::Int(i) => usize::try_from(*i).ok()?,
+                    ChoreoWaypointName::String(s) if s == "first" => 0,
+                    ChoreoWaypointName::String(s) if s == "last" => {
+                        self.trajectory.waypoints.len().checked_sub(1)?
+                    }
+                    ChoreoWaypointName::String(_) => return None,
+                };
+                *self.trajectory.waypoints.get(idx)?
+            }
+            (None, None) => return None,
+        };
+        Some(anchor_t + event.from.offset.val)
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct ChoreoTraj {
     #[serde(rename = "sampleType")]
     pub sample_type: Option<String>,
@@ -33,15 +273,153 @@ pub struct ChoreoTraj {
     pub splits: Vec<i32>,
 }
 
-impl Flippable for ChoreoTraj {
-    fn flip_alliance(&mut self) {
-        self.samples.iter_mut().for_each(Flippable::flip_alliance);
+impl ChoreoTraj {
+    pub fn duration(&self) -> f64 {
+        self.samples.last().map(|s| s.t).unwrap_or(0.0)
+    }
+
+    pub fn start_pose(&self) -> Option<Pose2d> {
+        self.samples.first().map(ChoreoSample::pose)
+    }
+
+    pub fn end_pose(&self) -> Option<Pose2d> {
+        self.samples.last().map(ChoreoSample::pose)
     }
 
-    fn flip_same_alliance(&mut self) {
-        self.samples
-            .iter_mut()
-            .for_each(Flippable::flip_same_alliance);
+    // state at time `t`, clamped to the trajectory. Like choreolib, the state is
+    // integrated forward from the previous sample with its acceleration rather than
+    // lerped, since choreo assumes constant acceleration between samples
+    pub fn sample_at(&self, t: f64) -> Option<ChoreoSample> {
+        let first = self.samples.first()?;
+        let last = self.samples.last()?;
+        if t <= first.t {
+            return Some(first.clone());
+        }
+        if t >= last.t {
+            return Some(last.clone());
+        }
+        let idx = self.samples.partition_point(|s| s.t <= t);
+        let (s0, s1) = (&self.samples[idx - 1], &self.samples[idx]);
+        let scale = if s1.t > s0.t {
+            (t - s0.t) / (s1.t - s0.t)
+        } else {
+            0.0
+        };
+        let tau = t - s0.t;
+        let lerp = |a: &Vec<f64>, b: &Vec<f64>| -> Vec<f64> {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a + (b - a) * scale)
+                .collect()
+        };
+        Some(ChoreoSample {
+            t,
+            x: s0.x + s0.vx * tau + 0.5 * s0.ax * tau * tau,
+            y: s0.y + s0.vy * tau + 0.5 * s0.ay * tau * tau,
+            heading: s0.heading + s0.omega * tau + 0.5 * s0.alpha * tau * tau,
+            vx: s0.vx + s0.ax * tau,
+            vy: s0.vy + s0.ay * tau,
+            omega: s0.omega + s0.alpha * tau,
+            ax: s0.ax,
+            ay: s0.ay,
+            alpha: s0.alpha,
+            fx: lerp(&s0.fx, &s1.fx),
+            fy: lerp(&s0.fy, &s1.fy),
+        })
+    }
+
+    // stretches the trajectory in time by `factor` (> 1 is slower). Velocities scale by
+    // 1/factor, accelerations and module forces by 1/factor^2
+    pub fn time_scale(&mut self, factor: f64) {
+        let factor_sq = factor * factor;
+        for s in self.samples.iter_mut() {
+            s.t *= factor;
+            s.vx /= factor;
+            s.vy /= factor;
+            s.omega /= factor;
+            s.ax /= factor_sq;
+            s.ay /= factor_sq;
+            s.alpha /= factor_sq;
+            s.fx.iter_mut().for_each(|f| *f /= factor_sq);
+            s.fy.iter_mut().for_each(|f| *f /= factor_sq);
+        }
+        self.waypoints.iter_mut().for_each(|t| *t *= factor);
+    }
+
+    // resamples each waypoint to waypoint segment into equal intervals no longer than `dt`
+    // the way choreo does, so every waypoint and split keeps a sample of its own. Returns
+    // the new interval count of each segment
+    pub fn resample(&mut self, dt: f64) -> Vec<i32> {
+        let bounds = if self.waypoints.len() >= 2 {
+            self.waypoints.clone()
+        } else {
+            vec![0.0, self.duration()]
+        };
+        let split_times: Vec<f64> = self
+            .splits
+            .iter()
+            .filter_map(|i| self.samples.get(*i as usize).map(|s| s.t))
+            .collect();
+        let mut times = vec![bounds[0]];
+        let mut intervals = Vec::new();
+        for w in bounds.windows(2) {
+            let count = ((w[1] - w[0]) / dt - 1e-9).ceil().max(1.0) as usize;
+            times.extend((1..=count).map(|i| w[0] + (w[1] - w[0]) * i as f64 / count as f64));
+            intervals.push(count as i32);
+        }
+        // each keeps the acceleration of the interval it falls in, as sample_at gives it,
+        // not a difference of velocities that may come from two different intervals
+        let samples: Vec<ChoreoSample> = times.iter().filter_map(|t| self.sample_at(*t)).collect();
+        self.splits = split_times
+            .iter()
+            .map(|t| samples.partition_point(|s| s.t < t - 1e-9) as i32)
+            .collect();
+        self.samples = samples;
+        intervals
+    }
+}
+
+impl ChoreoTraj {
+    // the samples run backwards in time. Velocities change sign and accelerations don't,
+    // but as each holds until the next sample, every sample takes the acceleration of
+    // the one after it
+    pub fn reverse(&mut self) {
+        let duration = self.duration();
+        self.samples.reverse();
+        for s in self.samples.iter_mut() {
+            s.t = duration - s.t;
+            s.vx = -s.vx;
+            s.vy = -s.vy;
+            s.omega = -s.omega;
+        }
+        for i in 0..self.samples.len().saturating_sub(1) {
+            let next = self.samples[i + 1].clone();
+            let s = &mut self.samples[i];
+            (s.ax, s.ay, s.alpha) = (next.ax, next.ay, next.alpha);
+            (s.fx, s.fy) = (next.fx, next.fy);
+        }
+        // the new end has no interval after it to accelerate over
+        if let Some(end) = self.samples.last_mut() {
+            (end.ax, end.ay, end.alpha) = (0.0, 0.0, 0.0);
+            end.fx.iter_mut().for_each(|f| *f = 0.0);
+            end.fy.iter_mut().for_each(|f| *f = 0.0);
+        }
+        self.waypoints.reverse();
+        self.waypoints.iter_mut().for_each(|t| *t = duration - *t);
+        // the first section still starts at the first sample
+        let last = self.samples.len().saturating_sub(1) as i32;
+        self.splits = self
+            .splits
+            .iter()
+            .map(|&i| if i == 0 { 0 } else { last - i })
+            .collect();
+        self.splits.sort();
+    }
+}
+
+impl Flippable for ChoreoTraj {
+    fn transform(&mut self, t: &Transform2d) {
+        self.samples.iter_mut().for_each(|s| s.transform(t));
     }
 }
 
@@ -54,14 +432,8 @@ pub struct ChoreoSnapshotData {
 }
 
 impl Flippable for ChoreoSnapshotData {
-    fn flip_alliance(&mut self) {
-        self.waypoints.iter_mut().for_each(Flippable::flip_alliance);
-    }
-
-    fn flip_same_alliance(&mut self) {
-        self.waypoints
-            .iter_mut()
-            .for_each(Flippable::flip_same_alliance);
+    fn transform(&mut self, t: &Transform2d) {
+        self.waypoints.iter_mut().for_each(|w| w.transform(t));
     }
 }
 
@@ -74,14 +446,8 @@ pub struct ChoreoParams {
 }
 
 impl Flippable for ChoreoParams {
-    fn flip_alliance(&mut self) {
-        self.waypoints.iter_mut().for_each(Flippable::flip_alliance);
-    }
-
-    fn flip_same_alliance(&mut self) {
-        self.waypoints
-            .iter_mut()
-            .for_each(Flippable::flip_same_alliance);
+    fn transform(&mut self, t: &Transform2d) {
+        self.waypoints.iter_mut().for_each(|w| w.transform(t));
     }
 }
 
@@ -98,24 +464,69 @@ pub struct ChoreoSWaypoint {
     pub fix_heading: bool,
     #[serde(rename = "overrideIntervals")]
     pub override_intervals: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Flippable for ChoreoSWaypoint {
-    fn flip_same_alliance(&mut self) {
-        self.y = flip::flip_xaxis(self.x, self.y)[1];
-        self.heading = if self.heading == 0.0 {
-            0.0
-        } else {
-            -self.heading
-        };
+    fn transform(&mut self, t: &Transform2d) {
+        [self.x, self.y] = t.point([self.x, self.y]);
+        self.heading = t.heading(self.heading);
     }
+}
 
-    fn flip_alliance(&mut self) {
-        self.x = flip::flip_yaxis(self.x, self.y)[0];
+// the settings a waypoint holds for the segment from it to the next waypoint
+trait SegmentStart {
+    fn segment(&mut self) -> (&mut i32, &mut bool);
+}
+
+impl SegmentStart for ChoreoSWaypoint {
+    fn segment(&mut self) -> (&mut i32, &mut bool) {
+        (&mut self.intervals, &mut self.override_intervals)
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+impl SegmentStart for ChoreoWaypoint {
+    fn segment(&mut self) -> (&mut i32, &mut bool) {
+        (&mut self.intervals, &mut self.override_intervals)
+    }
+}
+
+// reversed, each segment starts at the waypoint that used to end it
+fn reverse_waypoints(waypoints: &mut [impl SegmentStart]) {
+    waypoints.reverse();
+    let mut segments: Vec<(i32, bool)> = waypoints
+        .iter_mut()
+        .map(|w| {
+            let (intervals, overridden) = w.segment();
+            (*intervals, *overridden)
+        })
+        .collect();
+    segments.rotate_left(1);
+    for (w, (intervals, overridden)) in waypoints.iter_mut().zip(segments) {
+        let segment = w.segment();
+        (*segment.0, *segment.1) = (intervals, overridden);
+    }
+}
+
+// `last` is the index of the last waypoint
+fn reverse_constraints(constraints: &mut [ChoreoConstraint], last: usize) {
+    for c in constraints.iter_mut() {
+        let (from, to) = (c.from.take(), c.to.take());
+        match (from, to) {
+            (Some(from), Some(to)) => {
+                c.from = Some(to.reversed(last));
+                c.to = Some(from.reversed(last));
+            }
+            (from, to) => {
+                c.from = from.map(|f| f.reversed(last));
+                c.to = to.map(|t| t.reversed(last));
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct ChoreoWaypoint {
     pub x: ChoreoValue,
     pub y: ChoreoValue,
@@ -128,24 +539,26 @@ pub struct ChoreoWaypoint {
     pub fix_heading: bool,
     #[serde(rename = "overrideIntervals")]
     pub override_intervals: bool,
+    // what other choreo versions keep on a waypoint, moved with it when it's reversed
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Flippable for ChoreoWaypoint {
-    fn flip_same_alliance(&mut self) {
-        self.y.val = flip::flip_xaxis(self.x.val, self.y.val)[1];
-        self.y.update_exp("m");
-        self.heading.val = if self.heading.val == 0.0 {
-            0.0
-        } else {
-            -self.heading.val
-        };
-        self.heading.update_exp("rad");
-    }
-
-    fn flip_alliance(&mut self) {
-        self.x.val = flip::flip_yaxis(self.x.val, self.y.val)[0];
-        self.x.update_exp("m");
-        self.heading.update_exp("rad");
+    fn transform(&mut self, t: &Transform2d) {
+        match t.axis_maps() {
+            Some([x, y, heading]) => {
+                self.x.map(x, "m");
+                self.y.map(y, "m");
+                self.heading.map(heading, "rad");
+            }
+            None => {
+                let [x, y] = t.point([self.x.val, self.y.val]);
+                self.x.set(x, "m");
+                self.y.set(y, "m");
+                self.heading.set(t.heading(self.heading.val), "rad");
+            }
+        }
     }
 }
 
@@ -165,24 +578,20 @@ pub struct ChoreoSample {
     pub fy: Vec<f64>,
 }
 
-impl Flippable for ChoreoSample {
-    fn flip_same_alliance(&mut self) {
-        self.y = flip::flip_xaxis(self.x, self.y)[1];
-        self.vy = if self.vy == 0.0 { 0.0 } else { -self.vy };
-        self.ay = if self.ay == 0.0 { 0.0 } else { -self.ay };
-        self.heading = if self.heading == 0.0 {
-            0.0
-        } else {
-            -self.heading
-        };
-        self.omega = if self.omega == 0.0 { 0.0 } else { -self.omega };
-        self.alpha = if self.alpha == 0.0 { 0.0 } else { -self.alpha };
+impl ChoreoSample {
+    pub fn pose(&self) -> Pose2d {
+        Pose2d::new(self.x, self.y, self.heading)
     }
+}
 
-    fn flip_alliance(&mut self) {
-        self.x = flip::flip_yaxis(self.x, self.y)[0];
-        self.vx = if self.vx == 0.0 { 0.0 } else { -self.vx };
-        self.ax = if self.ax == 0.0 { 0.0 } else { -self.ax };
+impl Flippable for ChoreoSample {
+    fn transform(&mut self, t: &Transform2d) {
+        [self.x, self.y] = t.point([self.x, self.y]);
+        [self.vx, self.vy] = t.vector([self.vx, self.vy]);
+        [self.ax, self.ay] = t.vector([self.ax, self.ay]);
+        self.heading = t.heading(self.heading);
+        self.omega = t.rate(self.omega);
+        self.alpha = t.rate(self.alpha);
     }
 }
 
@@ -192,6 +601,8 @@ pub struct ChoreoConstraint {
     pub to: Option<ChoreoWaypointName>,
     pub data: ChoreoConstraintData,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -201,7 +612,7 @@ pub struct ChoreoConstraintData {
     pub props: serde_json::Value,
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct ChoreoValue {
     pub exp: String,
     pub val: f64,
@@ -209,13 +620,771 @@ pub struct ChoreoValue {
 
 impl ChoreoValue {
     pub fn update_exp(&mut self, unit: &str) {
-        self.exp = self.val.to_string() + " " + unit;
+        self.exp = expr::Expr::literal(self.val, unit).to_string();
+    }
+
+    // val -> offset + scale * val. Expressions referencing variables stay symbolic
+    // ("16.54 m - (ReefX + 0.3 m)"), plain literals are rewritten in their own unit
+    pub fn map(&mut self, map: AxisMap, unit: &str) {
+        if map.is_identity() {
+            return;
+        }
+        self.val = map.apply(self.val);
+        match expr::Expr::parse(&self.exp) {
+            Ok(e) if e.has_vars() => {
+                self.exp = expr::Expr::affine(map.offset, map.scale, unit, e).to_string();
+            }
+            _ => self.write_literal(unit),
+        }
+    }
+
+    // replaces the value, and the expression with a literal in the unit it was written in
+    pub fn set(&mut self, val: f64, unit: &str) {
+        self.val = val;
+        self.write_literal(unit);
+    }
+
+    fn write_literal(&mut self, unit: &str) {
+        match expr::Expr::parse(&self.exp) {
+            Ok(e) => {
+                let unit = e.literal_unit().unwrap_or(unit);
+                let factor = expr::unit_factor(unit).unwrap_or(1.0);
+                self.exp = expr::Expr::literal(self.val / factor, unit).to_string();
+            }
+            Err(_) => self.update_exp(unit),
+        }
+    }
+
+    // checks the stored value against the expression evaluated with the project variables
+    pub fn check(&self, scope: &expr::Scope) -> Result<(), String> {
+        let v = expr::Expr::parse_in(&self.exp, scope)?.eval(scope)?;
+        if (v - self.val).abs() > STALE_TOL {
+            Err(format!(
+                "`{}` evaluates to {} but {} is stored",
+                self.exp,
+                expr::format_number(v),
+                expr::format_number(self.val)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+const STALE_TOL: f64 = 1e-6;
+const STALE_POS_TOL_M: f64 = 1e-3;
+const STALE_HEADING_TOL_RAD: f64 = 1e-3;
+
+// replaces every {"exp", "val"} pair with its value so params can be compared against
+// the plain numbers stored in the snapshot
+fn strip_expressions(value: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) if map.len() == 2 && map.contains_key("exp") => {
+            map.get("val").cloned().unwrap_or(Value::Null)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), strip_expressions(v)))
+                .collect(),
+        ),
+        Value::Array(arr) => Value::Array(arr.iter().map(strip_expressions).collect()),
+        _ => value.clone(),
     }
 }
 
+// the variables table of a .chor project file
+#[derive(serde::Deserialize, Default)]
+pub struct ChoreoVariables {
+    #[serde(default)]
+    pub expressions: HashMap<String, ChoreoExpressionVar>,
+    #[serde(default)]
+    pub poses: HashMap<String, ChoreoPoseVar>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ChoreoExpressionVar {
+    // not written by every choreo version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<String>,
+    pub var: ChoreoValue,
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
+pub struct ChoreoPoseVar {
+    pub x: ChoreoValue,
+    pub y: ChoreoValue,
+    pub heading: ChoreoValue,
+}
+
+impl ChoreoVariables {
+    pub fn scope(&self) -> expr::Scope {
+        let mut scope = expr::Scope::default();
+        for (name, v) in &self.expressions {
+            scope.vars.insert(name.clone(), v.var.val);
+        }
+        for (name, pose) in &self.poses {
+            for axis in WaypointAxis::ALL {
+                scope
+                    .vars
+                    .insert(format!("{}.{}", name, axis.name()), pose.get(axis).val);
+            }
+        }
+        scope
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.expressions.contains_key(name) || self.poses.contains_key(name)
+    }
+}
+
+impl ChoreoPoseVar {
+    fn get(&self, axis: WaypointAxis) -> &ChoreoValue {
+        match axis {
+            WaypointAxis::X => &self.x,
+            WaypointAxis::Y => &self.y,
+            WaypointAxis::Heading => &self.heading,
+        }
+    }
+}
+
+// a .chor project file. Kept as json so everything besides the variables is written
+// back untouched
+pub struct ChoreoProject {
+    pub path: PathBuf,
+    pub json: serde_json::Value,
+}
+
+impl ChoreoProject {
+    // the .chor project a trajectory belongs to, if there is one, including edits `plan`
+    // already has for it
+    pub fn find(traj_path: &Path, plan: &WritePlan) -> error::Result<Option<ChoreoProject>> {
+        let Some(path) = project::Layout::discover(traj_path).choreo else {
+            return Ok(None);
+        };
+        let text = plan.read(&path)?;
+        let json = serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+            .map_err(|e| error::Error::parse(&path, e))?;
+        Ok(Some(ChoreoProject { path, json }))
+    }
+
+    pub fn variables(&self) -> ChoreoVariables {
+        self.json
+            .get("variables")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn insert_variable(&mut self, table: &str, name: &str, value: serde_json::Value) {
+        if let Some(vars) = self
+            .json
+            .get_mut("variables")
+            .and_then(|v| v.as_object_mut())
+        {
+            if let Some(map) = vars
+                .entry(table)
+                .or_insert_with(|| serde_json::Value::Object(Default::default()))
+                .as_object_mut()
+            {
+                map.insert(name.to_owned(), value);
+            }
+        }
+    }
+}
+
+pub const FLIPPED_VAR_SUFFIX: &str = "Flipped";
+
+#[derive(Clone, Copy, PartialEq)]
+enum WaypointAxis {
+    X,
+    Y,
+    Heading,
+}
+
+impl WaypointAxis {
+    const ALL: [WaypointAxis; 3] = [WaypointAxis::X, WaypointAxis::Y, WaypointAxis::Heading];
+
+    fn name(self) -> &'static str {
+        match self {
+            WaypointAxis::X => "x",
+            WaypointAxis::Y => "y",
+            WaypointAxis::Heading => "heading",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            WaypointAxis::X | WaypointAxis::Y => "m",
+            WaypointAxis::Heading => "rad",
+        }
+    }
+
+    // how the transform maps this axis, or None when it's left alone
+    fn map(self, maps: &[AxisMap; 3]) -> Option<AxisMap> {
+        Some(maps[self as usize]).filter(|m| !m.is_identity())
+    }
+}
+
+impl ChoreoWaypoint {
+    fn get(&self, axis: WaypointAxis) -> &ChoreoValue {
+        match axis {
+            WaypointAxis::X => &self.x,
+            WaypointAxis::Y => &self.y,
+            WaypointAxis::Heading => &self.heading,
+        }
+    }
+
+    fn get_mut(&mut self, axis: WaypointAxis) -> &mut ChoreoValue {
+        match axis {
+            WaypointAxis::X => &mut self.x,
+            WaypointAxis::Y => &mut self.y,
+            WaypointAxis::Heading => &mut self.heading,
+        }
+    }
+}
+
+// mirrored copies of the project variables referenced by a flipped trajectory. Each copy is
+// defined in terms of its original (ReefXFlipped = "16.54 m - ReefX") so editing the
+// original in choreo moves both
+struct VariableMirror {
+    vars: ChoreoVariables,
+    // of `vars`, to read waypoint expressions with
+    scope: expr::Scope,
+    maps: [AxisMap; 3],
+    // original pose name -> mirrored pose name
+    poses: HashMap<String, String>,
+    // original expression name -> (mirrored name, map it was mirrored with)
+    expressions: HashMap<String, (String, AxisMap)>,
+    new_vars: Vec<(&'static str, String, serde_json::Value)>,
+}
+
+impl VariableMirror {
+    // the first free "<name>Flipped", "<name>Flipped2", ... or an existing one that already
+    // holds the same definition
+    fn mirrored_name(&self, name: &str, matches: impl Fn(&str) -> bool) -> String {
+        (1..)
+            .map(|i| match i {
+                1 => format!("{}{}", name, FLIPPED_VAR_SUFFIX),
+                _ => format!("{}{}{}", name, FLIPPED_VAR_SUFFIX, i),
+            })
+            .find(|n| {
+                !self.new_vars.iter().any(|(_, v, _)| v == n)
+                    && (!self.vars.contains(n) || matches(n))
+            })
+            .unwrap()
+    }
+
+    fn mirror_pose(&mut self, name: &str) -> Option<String> {
+        if let Some(n) = self.poses.get(name) {
+            return Some(n.clone());
+        }
+        let orig = self.vars.poses.get(name)?;
+        let mut mirrored = serde_json::Map::new();
+        for axis in WaypointAxis::ALL {
+            let value = orig.get(axis);
+            let component = expr::Expr::Var(format!("{}.{}", name, axis.name()));
+            let (exp, val) = match axis.map(&self.maps) {
+                Some(map) => (
+                    expr::Expr::affine(map.offset, map.scale, axis.unit(), component),
+                    map.apply(value.val),
+                ),
+                None => (component, value.val),
+            };
+            mirrored.insert(
+                axis.name().to_owned(),
+                serde_json::json!({"exp": exp.to_string(), "val": val}),
+            );
+        }
+        let mirrored = serde_json::Value::Object(mirrored);
+        let new_name = self.mirrored_name(name, |n| {
+            self.vars.poses.get(n).is_some_and(|p| {
+                WaypointAxis::ALL
+                    .iter()
+                    .all(|a| mirrored[a.name()]["exp"] == p.get(*a).exp.as_str())
+            })
+        });
+        if !self.vars.poses.contains_key(&new_name) {
+            self.new_vars.push(("poses", new_name.clone(), mirrored));
+        }
+        self.poses.insert(name.to_owned(), new_name.clone());
+        Some(new_name)
+    }
+
+    fn mirror_expression(&mut self, name: &str, map: AxisMap, unit: &str) -> Option<String> {
+        if let Some((n, m)) = self.expressions.get(name) {
+            return (*m == map).then(|| n.clone());
+        }
+        let orig = self.vars.expressions.get(name)?;
+        let exp = expr::Expr::affine(
+            map.offset,
+            map.scale,
+            unit,
+            expr::Expr::Var(name.to_owned()),
+        )
+        .to_string();
+        let mirrored = serde_json::to_value(ChoreoExpressionVar {
+            dimension: orig.dimension.clone(),
+            var: ChoreoValue {
+                exp: exp.clone(),
+                val: map.apply(orig.var.val),
+            },
+        })
+        .ok()?;
+        let new_name = self.mirrored_name(name, |n| {
+            self.vars
+                .expressions
+                .get(n)
+                .is_some_and(|e| e.var.exp == exp)
+        });
+        if !self.vars.expressions.contains_key(&new_name) {
+            self.new_vars
+                .push(("expressions", new_name.clone(), mirrored));
+        }
+        self.expressions
+            .insert(name.to_owned(), (new_name.clone(), map));
+        Some(new_name)
+    }
+
+    // rewrites a mapped waypoint expression "P + offsets" as "P' - offsets", or as
+    // "P' + offsets" when the axis isn't reflected, where P is the one variable carrying the
+    // position and P' its mirrored copy
+    fn map(&mut self, exp: &str, axis: WaypointAxis) -> Option<String> {
+        let map = axis.map(&self.maps)?;
+        let e = expr::Expr::parse_in(exp, &self.scope).ok()?;
+        let refs = e.var_refs();
+        let pose_refs: Vec<&String> = refs
+            .iter()
+            .filter(|r| {
+                r.split_once('.').is_some_and(|(pose, component)| {
+                    component == axis.name() && self.vars.poses.contains_key(pose)
+                })
+            })
+            .collect();
+        let (point, replacement) = match (pose_refs.as_slice(), refs.as_slice()) {
+            ([r], _) => {
+                let (pose, component) = r.split_once('.')?;
+                let point = r.to_string();
+                (point, format!("{}.{}", self.mirror_pose(pose)?, component))
+            }
+            ([], [r]) if self.vars.expressions.contains_key(r) => {
+                (r.clone(), self.mirror_expression(r, map, axis.unit())?)
+            }
+            _ => return None,
+        };
+        Some(map_about_var(&e, &point, &replacement, map.scale < 0.0)?.to_string())
+    }
+
+    // points components that the flip leaves alone at the mirrored pose as well, so a
+    // waypoint keeps following a single named pose
+    fn follow(&self, exp: &str, axis: WaypointAxis) -> Option<String> {
+        let e = expr::Expr::parse_in(exp, &self.scope).ok()?;
+        let renamed = e.rename_vars(&|r| {
+            let (pose, component) = r.split_once('.')?;
+            let unchanged = WaypointAxis::ALL
+                .iter()
+                .any(|a| a.name() == component && a.map(&self.maps).is_none());
+            self.poses
+                .get(pose)
+                .filter(|_| unchanged)
+                .map(|p| format!("{}.{}", p, component))
+        });
+        (renamed != e && axis.map(&self.maps).is_none()).then(|| renamed.to_string())
+    }
+}
+
+// e with `point` replaced, written through the additive terms of e. Reflected, the terms
+// change sign: P + a - b -> P' - a + b
+fn map_about_var(
+    e: &expr::Expr,
+    point: &str,
+    replacement: &str,
+    reflected: bool,
+) -> Option<expr::Expr> {
+    use expr::{Expr, Op};
+    let has_point = |e: &Expr| e.var_refs().iter().any(|r| r == point);
+    let map = |e: &Expr| map_about_var(e, point, replacement, reflected).map(Box::new);
+    let (add, sub) = if reflected {
+        (Op::Sub, Op::Add)
+    } else {
+        (Op::Add, Op::Sub)
+    };
+    match e {
+        Expr::Var(v) if v == point => Some(Expr::Var(replacement.to_owned())),
+        Expr::Bin(Op::Add, a, b) if has_point(a) => Some(Expr::Bin(add, map(a)?, b.clone())),
+        Expr::Bin(Op::Add, a, b) if has_point(b) && reflected => {
+            Some(Expr::Bin(Op::Sub, map(b)?, a.clone()))
+        }
+        Expr::Bin(Op::Add, a, b) if has_point(b) => Some(Expr::Bin(Op::Add, a.clone(), map(b)?)),
+        Expr::Bin(Op::Sub, a, b) if has_point(a) => Some(Expr::Bin(sub, map(a)?, b.clone())),
+        _ => None,
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ChoreoEvent {
+    pub name: String,
+    pub from: ChoreoEventAnchor,
+    pub event: Option<PPCommand>,
+    // whatever else choreo keeps on an event, written back as it was read
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ChoreoEvent {
+    pub fn named_commands(&self) -> Vec<String> {
+        self.event
+            .as_ref()
+            .map(|c| c.named_commands())
+            .unwrap_or_default()
+    }
+
+    // follows the files in `renamed`, by file stem, under their new stems as the auto does.
+    // Paths that weren't flipped with it are followed as before
+    pub fn rename_paths(&mut self, renamed: &HashMap<String, String>) {
+        if let Some(command) = self.event.as_mut() {
+            let mut names = auto::renamed_paths(command, renamed);
+            for name in command.path_names() {
+                names.entry(name.clone()).or_insert(name);
+            }
+            if let Ok(new_command) = command.replace_path_commands(&names) {
+                *command = new_command;
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ChoreoEventAnchor {
+    pub target: Option<ChoreoWaypointName>,
+    #[serde(rename = "targetTimestamp")]
+    pub target_timestamp: Option<f64>,
+    pub offset: ChoreoValue,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 #[serde(untagged)]
 pub enum ChoreoWaypointName {
     String(String),
     Int(i32),
 }
+
+impl ChoreoWaypointName {
+    // the same waypoint counted from the other end, `last` being the index of the last
+    fn reversed(&self, last: usize) -> ChoreoWaypointName {
+        match self {
+            ChoreoWaypointName::Int(i) => ChoreoWaypointName::Int(last as i32 - i),
+            ChoreoWaypointName::String(s) if s == "first" => {
+                ChoreoWaypointName::String("last".to_owned())
+            }
+            ChoreoWaypointName::String(s) if s == "last" => {
+                ChoreoWaypointName::String("first".to_owned())
+            }
+            ChoreoWaypointName::String(_) => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_without_a_dimension_are_read() {
+        let project = ChoreoProject {
+            path: PathBuf::from("proj.chor"),
+            json: serde_json::json!({
+                "variables": {
+                    "expressions": {
+                        "ReefX": { "dimension": "Length", "var": { "exp": "4.5 m", "val": 4.5 } },
+                        "Gap": { "var": { "exp": "0.3 m", "val": 0.3 } }
+                    },
+                    "poses": {}
+                }
+            }),
+        };
+        let vars = project.variables();
+        assert_eq!(vars.expressions.len(), 2);
+        assert!(vars.expressions["Gap"].dimension.is_none());
+        assert_eq!(vars.scope().vars["Gap"], 0.3);
+    }
+
+    #[test]
+    fn events_keep_fields_they_dont_model() {
+        let json = serde_json::json!({
+            "name": "Intake",
+            "from": {
+                "target": 1,
+                "targetTimestamp": 0.5,
+                "offset": { "exp": "0 s", "val": 0.0 },
+                "note": "anchor"
+            },
+            "event": { "type": "named", "data": { "name": "intake" } },
+            "color": "red"
+        });
+        let event: ChoreoEvent = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&event).unwrap(), json);
+    }
+
+    #[test]
+    fn events_follow_the_paths_flipped_with_the_auto() {
+        let follow =
+            |name: &str| serde_json::json!({ "type": "path", "data": { "pathName": name } });
+        let event = |commands: Vec<serde_json::Value>| {
+            serde_json::json!({
+                "name": "Score",
+                "from": { "target": 0, "targetTimestamp": 0.0, "offset": { "exp": "0 s", "val": 0.0 } },
+                "event": { "type": "sequential", "data": { "commands": commands } }
+            })
+        };
+        let mut flipped: ChoreoEvent =
+            serde_json::from_value(event(vec![follow("Shot.1"), follow("Feed")])).unwrap();
+        let renamed = HashMap::from([("Shot".to_owned(), "Shot Flipped".to_owned())]);
+        flipped.rename_paths(&renamed);
+        // a segment keeps its split, and a path that wasn't flipped is followed as it was
+        assert_eq!(
+            serde_json::to_value(&flipped).unwrap(),
+            event(vec![follow("Shot Flipped.1"), follow("Feed")])
+        );
+    }
+
+    fn sample(t: f64, x: f64, vx: f64, ax: f64) -> ChoreoSample {
+        ChoreoSample {
+            t,
+            x,
+            y: 1.0,
+            heading: 0.5 * t,
+            vx,
+            vy: 0.0,
+            omega: 0.5,
+            ax,
+            ay: 0.0,
+            alpha: 0.0,
+            fx: vec![ax; 4],
+            fy: vec![0.0; 4],
+        }
+    }
+
+    // speeds up at 2 m/s^2 for a second, then slows down at 1 m/s^2 for two
+    fn traj() -> ChoreoTraj {
+        ChoreoTraj {
+            sample_type: Some("Swerve".to_owned()),
+            waypoints: vec![0.0, 1.0, 3.0],
+            samples: vec![
+                sample(0.0, 0.0, 0.0, 2.0),
+                sample(0.5, 0.25, 1.0, 2.0),
+                sample(1.0, 1.0, 2.0, -1.0),
+                sample(2.0, 2.5, 1.0, -1.0),
+                sample(3.0, 3.0, 0.0, 0.0),
+            ],
+            splits: vec![0, 2],
+        }
+    }
+
+    #[test]
+    fn edits_since_generation_are_warned_about() {
+        let mut data = choreo();
+        // the samples turn towards the end, so the last heading can't be fixed
+        data.snapshot.waypoints[2].fix_heading = false;
+        data.params.waypoints[2].fix_heading = false;
+        assert!(data.stale_reasons().is_empty());
+
+        data.params.waypoints[1].x.val += 0.5;
+        data.params.target_dt.val = 0.1;
+        data.params.constraints.clear();
+        data.trajectory.samples[4].y += 0.2;
+        assert_eq!(
+            data.stale_reasons(),
+            vec![
+                "waypoint 2 changed since generation (x)",
+                "targetDt is 0.1 but trajectory was generated with 0.05",
+                "constraints changed since generation",
+                "samples end 0.200 m / 85.9\u{b0} away from the snapshot waypoint",
+            ]
+        );
+    }
+
+    #[test]
+    fn states_between_samples_follow_their_acceleration() {
+        let traj = traj();
+        assert_eq!(traj.duration(), 3.0);
+        assert_eq!(traj.start_pose().unwrap().x, 0.0);
+        assert_eq!(traj.end_pose().unwrap().x, 3.0);
+        // integrated from the sample before, not lerped towards the one after
+        let s = traj.sample_at(1.5).unwrap();
+        assert!((s.x - 1.875).abs() < 1e-12 && (s.vx - 1.5).abs() < 1e-12);
+        assert!((s.heading - 0.75).abs() < 1e-12);
+        assert_eq!((s.ax, s.fx.clone()), (-1.0, vec![-1.0; 4]));
+        assert!((traj.sample_at(0.25).unwrap().x - 0.0625).abs() < 1e-12);
+        // and clamped to the ends
+        assert_eq!(traj.sample_at(-1.0).unwrap().t, 0.0);
+        assert_eq!(traj.sample_at(5.0).unwrap().x, 3.0);
+        let empty = ChoreoTraj {
+            samples: Vec::new(),
+            ..traj
+        };
+        assert!(empty.sample_at(1.0).is_none());
+    }
+
+    fn choreo() -> ChoreoData {
+        let waypoint = |x: f64, intervals: i32| {
+            serde_json::json!({
+                "x": x,
+                "y": 1.0,
+                "heading": 0.0,
+                "intervals": intervals,
+                "split": false,
+                "fixTranslation": true,
+                "fixHeading": true,
+                "overrideIntervals": false
+            })
+        };
+        let waypoints = [waypoint(0.0, 3), waypoint(1.0, 5), waypoint(3.0, 40)];
+        let constraints = serde_json::json!([{
+            "from": "first",
+            "to": 1,
+            "data": { "type": "MaxVelocity", "props": {} },
+            "enabled": true
+        }]);
+        let snapshot = serde_json::json!({
+            "waypoints": waypoints,
+            "constraints": constraints,
+            "targetDt": 0.05
+        });
+        let mut params = snapshot.clone();
+        for w in params["waypoints"].as_array_mut().unwrap() {
+            for key in ["x", "y", "heading"] {
+                let val = w[key].clone();
+                w[key] = serde_json::json!({ "exp": format!("{} m", val), "val": val });
+            }
+        }
+        params["targetDt"] = serde_json::json!({ "exp": "0.05 s", "val": 0.05 });
+        let event = |target: serde_json::Value, timestamp: serde_json::Value, offset: f64| {
+            serde_json::json!({
+                "name": "Intake",
+                "from": {
+                    "target": target,
+                    "targetTimestamp": timestamp,
+                    "offset": { "exp": format!("{} s", offset), "val": offset }
+                },
+                "event": null
+            })
+        };
+        ChoreoData {
+            name: "T".to_owned(),
+            version: 1,
+            snapshot: serde_json::from_value(snapshot).unwrap(),
+            params: serde_json::from_value(params).unwrap(),
+            trajectory: traj(),
+            events: vec![
+                serde_json::from_value(event(1.into(), 1.0.into(), 0.2)).unwrap(),
+                serde_json::from_value(event("first".into(), serde_json::Value::Null, 0.5))
+                    .unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn reversed_trajectories_run_from_the_end() {
+        let mut data = choreo();
+        data.reverse();
+        let xs: Vec<f64> = data.params.waypoints.iter().map(|w| w.x.val).collect();
+        assert_eq!(xs, vec![3.0, 1.0, 0.0]);
+        let xs: Vec<f64> = data.snapshot.waypoints.iter().map(|w| w.x).collect();
+        assert_eq!(xs, vec![3.0, 1.0, 0.0]);
+        // each segment keeps its intervals from the waypoint that now starts it
+        let intervals: Vec<i32> = data.params.waypoints.iter().map(|w| w.intervals).collect();
+        assert_eq!(intervals, vec![5, 3, 40]);
+        let c = &data.params.constraints[0];
+        assert!(matches!(c.from, Some(ChoreoWaypointName::Int(1))));
+        assert!(matches!(&c.to, Some(ChoreoWaypointName::String(s)) if s == "last"));
+
+        let traj = &data.trajectory;
+        assert_eq!(traj.waypoints, vec![0.0, 2.0, 3.0]);
+        assert_eq!(traj.splits, vec![0, 2]);
+        let samples: Vec<_> = traj
+            .samples
+            .iter()
+            .map(|s| (s.t, s.x, s.vx, s.ax))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![
+                (0.0, 3.0, 0.0, -1.0),
+                (1.0, 2.5, -1.0, -1.0),
+                (2.0, 1.0, -2.0, 2.0),
+                (2.5, 0.25, -1.0, 2.0),
+                (3.0, 0.0, 0.0, 0.0),
+            ]
+        );
+        assert_eq!(traj.samples[4].fx, vec![0.0; 4]);
+
+        // as long before the end as they were after the start
+        let times: Vec<f64> = data
+            .events
+            .iter()
+            .map(|e| data.event_timestamp(e).unwrap())
+            .collect();
+        let expected = [3.0 - 0.5, 3.0 - 1.2];
+        for (t, expected) in times.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-9, "{} != {}", t, expected);
+        }
+    }
+
+    #[test]
+    fn retimed_events_keep_their_variables() {
+        let mut data = choreo();
+        data.events[0].from.offset = ChoreoValue {
+            exp: "EventDelay".to_owned(),
+            val: 0.2,
+        };
+        data.time_scale(2.0);
+        assert_eq!(data.trajectory.waypoints, vec![0.0, 2.0, 6.0]);
+        assert_eq!(data.trajectory.splits, vec![0, 2]);
+        assert!((data.trajectory.duration() - 6.0).abs() < 1e-9);
+        let offset = &data.events[0].from.offset;
+        assert!(offset.exp.contains("EventDelay"), "{}", offset.exp);
+        assert!((offset.val - 0.4).abs() < 1e-9);
+        let scope = expr::Scope {
+            vars: HashMap::from([("EventDelay".to_owned(), 0.2)]),
+        };
+        assert!(offset.check(&scope).is_ok());
+        let times: Vec<f64> = data
+            .events
+            .iter()
+            .map(|e| data.event_timestamp(e).unwrap())
+            .collect();
+        for (t, expected) in times.iter().zip([2.4, 1.0]) {
+            assert!((t - expected).abs() < 1e-9, "{} != {}", t, expected);
+        }
+    }
+
+    #[test]
+    fn resampling_keeps_duration_and_endpoints() {
+        let mut traj = traj();
+        let (start, end) = (traj.start_pose().unwrap(), traj.end_pose().unwrap());
+        assert_eq!(traj.resample(0.3), vec![4, 7]);
+        assert!((traj.duration() - 3.0).abs() < 1e-9);
+        for (a, b) in [
+            (traj.start_pose().unwrap(), start),
+            (traj.end_pose().unwrap(), end),
+        ] {
+            assert!((a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9);
+            assert!((a.heading - b.heading).abs() < 1e-9);
+        }
+        // the split at the second waypoint still has a sample of its own
+        assert_eq!(traj.splits, vec![0, 4]);
+        assert!((traj.samples[4].t - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn resampled_accelerations_are_the_intervals_own() {
+        let mut traj = traj();
+        traj.resample(0.3);
+        for s in &traj.samples[..traj.samples.len() - 1] {
+            let expected = if s.t < 1.0 - 1e-9 { 2.0 } else { -1.0 };
+            assert_eq!(s.ax, expected, "at {}", s.t);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+// everything loading, flipping and writing can fail with, always tied to the file involved
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // the file isn't valid json, or doesn't match the model. `pointer` is a json pointer
+    // ("/waypoints/2/anchor/x"), line and column are known when the text was parsed
+    // directly rather than after a schema upgrade
+    Parse {
+        path: PathBuf,
+        pointer: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    // a schema version this tool can't read
+    Schema {
+        path: PathBuf,
+        message: String,
+    },
+    // the file isn't where choreo or pathplanner would put it, or a file it refers to is
+    // missing
+    Layout {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn schema(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Error::Schema {
+            path: path.as_ref().to_path_buf(),
+            message: message.into(),
+        }
+    }
+
+    pub fn layout(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Error::Layout {
+            path: path.as_ref().to_path_buf(),
+            message: message.into(),
+        }
+    }
+
+    // a serde_json error, with the path serde_path_to_error tracked down to the failure
+    pub fn parse(
+        path: impl AsRef<Path>,
+        err: serde_path_to_error::Error<serde_json::Error>,
+    ) -> Self {
+        let pointer = json_pointer(err.path());
+        let inner = err.into_inner();
+        if inner.is_io() {
+            return Error::Io {
+                path: path.as_ref().to_path_buf(),
+                source: inner.into(),
+            };
+        }
+        // serde_json reports line 0 when it wasn't reading text, and otherwise ends its
+        // message with the location, which Display already puts after the path
+        let mut message = inner.to_string();
+        let (line, column) = match inner.line() {
+            0 => (None, None),
+            line => {
+                let at = format!(" at line {} column {}", line, inner.column());
+                if message.ends_with(&at) {
+                    message.truncate(message.len() - at.len());
+                }
+                (Some(line), Some(inner.column()))
+            }
+        };
+        Error::Parse {
+            path: path.as_ref().to_path_buf(),
+            pointer,
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+// "waypoints[2].anchor.x" as "/waypoints/2/anchor/x"
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+    path.iter()
+        .filter_map(|seg| match seg {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .map(|s| format!("/{}", s))
+        .collect()
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse {
+                path,
+                pointer,
+                line,
+                column,
+                message,
+            } => {
+                write!(f, "{}", path.display())?;
+                if let (Some(line), Some(column)) = (line, column) {
+                    write!(f, ":{}:{}", line, column)?;
+                }
+                if !pointer.is_empty() {
+                    write!(f, " at {}", pointer)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Schema { path, message } | Error::Layout { path, message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// attaches the file an io error happened on
+pub trait WithPath<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T>;
+}
+
+impl<T> WithPath<T> for io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
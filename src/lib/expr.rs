@@ -0,0 +1,585 @@
+use std::{collections::HashMap, f64::consts::PI, fmt};
+
+// choreo expressions are mathjs strings such as "ReefX + 0.3 m", "180 deg" or
+// "Pose1.x". Values are evaluated into SI units (m, rad, s, kg)
+
+const UNITS: [(&str, f64); 21] = [
+    ("m", 1.0),
+    ("cm", 0.01),
+    ("mm", 0.001),
+    ("km", 1000.0),
+    ("in", 0.0254),
+    ("inch", 0.0254),
+    ("ft", 0.3048),
+    ("rad", 1.0),
+    ("deg", PI / 180.0),
+    ("rot", 2.0 * PI),
+    ("s", 1.0),
+    ("ms", 0.001),
+    ("min", 60.0),
+    ("kg", 1.0),
+    ("g", 0.001),
+    ("lb", 0.453_592_37),
+    ("lbs", 0.453_592_37),
+    ("N", 1.0),
+    ("A", 1.0),
+    ("V", 1.0),
+    ("RPM", 2.0 * PI / 60.0),
+];
+
+pub fn unit_factor(unit: &str) -> Option<f64> {
+    UNITS.iter().find(|(u, _)| *u == unit).map(|(_, f)| *f)
+}
+
+// prints a number without float noise, e.g. 0.30000000000000004 -> 0.3 and -0 -> 0
+pub fn format_number(v: f64) -> String {
+    let rounded = (v * 1e9).round() / 1e9;
+    if rounded == 0.0 {
+        "0".to_owned()
+    } else {
+        rounded.to_string()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Scope {
+    // variables by name, pose variables are flattened to "Pose.x", "Pose.y", "Pose.heading"
+    pub vars: HashMap<String, f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    // juxtaposition like "0.3 m", kept apart from `*` so it prints back the same way
+    ImplicitMul,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Unit(String),
+    Var(String),
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    // without the project's variables at hand, so a unit name is only read as a unit where
+    // it's applied to a value ("0.3 m", "2 m / s"), and as a variable anywhere else
+    pub fn parse(src: &str) -> Result<Expr, String> {
+        Ok(Self::parse_tokens(src)?.resolve(None, false))
+    }
+
+    // like mathjs, the variables declared in `scope` come before units of the same name
+    pub fn parse_in(src: &str, scope: &Scope) -> Result<Expr, String> {
+        Ok(Self::parse_tokens(src)?.resolve(Some(scope), false))
+    }
+
+    fn parse_tokens(src: &str) -> Result<Expr, String> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(t) => Err(format!("unexpected {:?} in `{}`", t, src)),
+        }
+    }
+
+    // turns the units the parser read that are variables into variables. `applied` is
+    // whether the expression is multiplied onto or divides a value
+    fn resolve(self, scope: Option<&Scope>, applied: bool) -> Expr {
+        let resolve = |e: Box<Expr>, applied| Box::new(e.resolve(scope, applied));
+        match self {
+            Expr::Unit(name) => {
+                let is_var = match scope {
+                    Some(scope) => scope.vars.contains_key(&name),
+                    None => !applied,
+                };
+                if is_var {
+                    Expr::Var(name)
+                } else {
+                    Expr::Unit(name)
+                }
+            }
+            Expr::Num(_) | Expr::Var(_) => self,
+            Expr::Neg(e) => Expr::Neg(resolve(e, false)),
+            Expr::Bin(op @ (Op::Mul | Op::Div | Op::ImplicitMul), l, r) => {
+                Expr::Bin(op, resolve(l, false), resolve(r, true))
+            }
+            Expr::Bin(Op::Pow, l, r) => Expr::Bin(Op::Pow, resolve(l, applied), resolve(r, false)),
+            Expr::Bin(op, l, r) => Expr::Bin(op, resolve(l, false), resolve(r, false)),
+            Expr::Call(name, args) => Expr::Call(
+                name,
+                args.into_iter().map(|a| a.resolve(scope, false)).collect(),
+            ),
+        }
+    }
+
+    // `value unit`, e.g. "1.2 m"
+    pub fn literal(value: f64, unit: &str) -> Expr {
+        let num = if value < 0.0 && format_number(value) != "0" {
+            Expr::Neg(Box::new(Expr::Num(-value)))
+        } else {
+            Expr::Num(value)
+        };
+        Expr::Bin(
+            Op::ImplicitMul,
+            Box::new(num),
+            Box::new(Expr::Unit(unit.to_owned())),
+        )
+    }
+
+    // `offset + scale * e` written the way a person would, e.g. "16.54 m - (ReefX + 0.3 m)"
+    // or "0.01 m + 1.002 * ReefX". An `e` written this way already is folded in, so a value
+    // mapped back gets its expression back instead of one nested in the other
+    pub fn affine(offset: f64, scale: f64, unit: &str, e: Expr) -> Expr {
+        let (inner_offset, inner_scale, e) = e.split_affine(unit);
+        let (offset, scale) = (offset + scale * inner_offset, scale * inner_scale);
+        let e = if format_number(scale.abs()) == "1" {
+            e
+        } else {
+            Expr::Bin(Op::Mul, Box::new(Expr::Num(scale.abs())), Box::new(e))
+        };
+        let signed = if scale >= 0.0 {
+            e
+        } else if let Expr::Neg(inner) = e {
+            *inner
+        } else {
+            Expr::Neg(Box::new(e))
+        };
+        if format_number(offset) == "0" {
+            return signed;
+        }
+        match signed {
+            Expr::Neg(inner) => Expr::Bin(Op::Sub, Box::new(Expr::literal(offset, unit)), inner),
+            e => Expr::Bin(Op::Add, Box::new(Expr::literal(offset, unit)), Box::new(e)),
+        }
+    }
+
+    // the offset, scale and rest of an expression shaped like the ones affine writes
+    fn split_affine(self, unit: &str) -> (f64, f64, Expr) {
+        let (offset, rest) = match self {
+            Expr::Bin(op @ (Op::Add | Op::Sub), l, r) => match l.literal_value(unit) {
+                Some(offset) if op == Op::Add => (offset, *r),
+                Some(offset) => (offset, Expr::Neg(r)),
+                None => return (0.0, 1.0, Expr::Bin(op, l, r)),
+            },
+            e => (0.0, e),
+        };
+        let (sign, rest) = match rest {
+            Expr::Neg(inner) => (-1.0, *inner),
+            e => (1.0, e),
+        };
+        match rest {
+            Expr::Bin(Op::Mul, l, r) => match *l {
+                Expr::Num(scale) => (offset, sign * scale, *r),
+                l => (offset, sign, Expr::Bin(Op::Mul, Box::new(l), r)),
+            },
+            e => (offset, sign, e),
+        }
+    }
+
+    // the number of a plain literal in `unit`
+    fn literal_value(&self, unit: &str) -> Option<f64> {
+        let Expr::Bin(Op::ImplicitMul, l, _) = self else {
+            return None;
+        };
+        match (l.as_ref(), self.literal_unit()) {
+            (Expr::Num(v), Some(u)) if u == unit => Some(*v),
+            (Expr::Neg(n), Some(u)) if u == unit => match n.as_ref() {
+                Expr::Num(v) => Some(-v),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn has_vars(&self) -> bool {
+        match self {
+            Expr::Var(name) => name != "pi" && name != "e",
+            Expr::Num(_) | Expr::Unit(_) => false,
+            Expr::Neg(e) => e.has_vars(),
+            Expr::Bin(_, l, r) => l.has_vars() || r.has_vars(),
+            Expr::Call(_, args) => args.iter().any(Expr::has_vars),
+        }
+    }
+
+    // every variable reference, in order and with repeats
+    pub fn var_refs(&self) -> Vec<String> {
+        match self {
+            Expr::Var(name) if self.has_vars() => vec![name.clone()],
+            Expr::Num(_) | Expr::Unit(_) | Expr::Var(_) => Vec::new(),
+            Expr::Neg(e) => e.var_refs(),
+            Expr::Bin(_, l, r) => [l.var_refs(), r.var_refs()].concat(),
+            Expr::Call(_, args) => args.iter().flat_map(Expr::var_refs).collect(),
+        }
+    }
+
+    // replaces the variables `f` returns a new name for
+    pub fn rename_vars(&self, f: &dyn Fn(&str) -> Option<String>) -> Expr {
+        match self {
+            Expr::Var(name) => Expr::Var(f(name).unwrap_or(name.clone())),
+            Expr::Num(_) | Expr::Unit(_) => self.clone(),
+            Expr::Neg(e) => Expr::Neg(Box::new(e.rename_vars(f))),
+            Expr::Bin(op, l, r) => {
+                Expr::Bin(*op, Box::new(l.rename_vars(f)), Box::new(r.rename_vars(f)))
+            }
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(|a| a.rename_vars(f)).collect(),
+            ),
+        }
+    }
+
+    // the unit of a plain literal like "180 deg"
+    pub fn literal_unit(&self) -> Option<&str> {
+        match self {
+            Expr::Bin(Op::ImplicitMul, l, r) => match (l.as_ref(), r.as_ref()) {
+                (Expr::Num(_), Expr::Unit(u)) => Some(u),
+                (Expr::Neg(n), Expr::Unit(u)) if matches!(n.as_ref(), Expr::Num(_)) => Some(u),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, scope: &Scope) -> Result<f64, String> {
+        Ok(match self {
+            Expr::Num(v) => *v,
+            Expr::Unit(u) => unit_factor(u).ok_or(format!("unknown unit {}", u))?,
+            Expr::Var(name) => match (scope.vars.get(name), name.as_str()) {
+                (Some(v), _) => *v,
+                (None, "pi") => PI,
+                (None, "e") => std::f64::consts::E,
+                _ => return Err(format!("unknown variable {}", name)),
+            },
+            Expr::Neg(e) => -e.eval(scope)?,
+            Expr::Bin(op, l, r) => {
+                let (l, r) = (l.eval(scope)?, r.eval(scope)?);
+                match op {
+                    Op::Add => l + r,
+                    Op::Sub => l - r,
+                    Op::Mul | Op::ImplicitMul => l * r,
+                    Op::Div => l / r,
+                    Op::Pow => l.powf(r),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(scope))
+                    .collect::<Result<Vec<f64>, String>>()?;
+                match (name.as_str(), args.as_slice()) {
+                    ("sin", [a]) => a.sin(),
+                    ("cos", [a]) => a.cos(),
+                    ("tan", [a]) => a.tan(),
+                    ("asin", [a]) => a.asin(),
+                    ("acos", [a]) => a.acos(),
+                    ("atan", [a]) => a.atan(),
+                    ("atan2", [y, x]) => y.atan2(*x),
+                    ("sqrt", [a]) => a.sqrt(),
+                    ("abs", [a]) => a.abs(),
+                    ("min", [a, b]) => a.min(*b),
+                    ("max", [a, b]) => a.max(*b),
+                    _ => return Err(format!("unknown function {}", name)),
+                }
+            }
+        })
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Bin(Op::Add | Op::Sub, ..) => 1,
+            Expr::Bin(Op::Mul | Op::Div, ..) => 2,
+            Expr::Bin(Op::ImplicitMul, ..) => 3,
+            Expr::Neg(_) => 4,
+            Expr::Bin(Op::Pow, ..) => 5,
+            _ => 6,
+        }
+    }
+}
+
+fn write_child(f: &mut fmt::Formatter<'_>, e: &Expr, min_prec: u8) -> fmt::Result {
+    if e.precedence() < min_prec {
+        write!(f, "({})", e)
+    } else {
+        write!(f, "{}", e)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(v) => write!(f, "{}", format_number(*v)),
+            Expr::Unit(u) | Expr::Var(u) => write!(f, "{}", u),
+            Expr::Neg(e) => {
+                write!(f, "-")?;
+                write_child(f, e, 4)
+            }
+            Expr::Bin(op, l, r) => {
+                let (sym, left_prec, right_prec) = match op {
+                    Op::Add => (" + ", 1, 1),
+                    Op::Sub => (" - ", 1, 2),
+                    Op::Mul => (" * ", 2, 2),
+                    Op::Div => (" / ", 2, 3),
+                    Op::ImplicitMul => (" ", 3, 5),
+                    Op::Pow => (" ^ ", 6, 5),
+                };
+                write_child(f, l, left_prec)?;
+                write!(f, "{}", sym)?;
+                write_child(f, r, right_prec)
+            }
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, a) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", a)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Sym(char),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // exponent, only when followed by digits so "2 e" stays euler's number
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(
+                text.parse().map_err(|_| format!("bad number {}", text))?,
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/^(),.".contains(c) {
+            tokens.push(Token::Sym(c));
+            i += 1;
+        } else {
+            return Err(format!("unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Sym(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.factor()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while matches!(
+            self.peek(),
+            Some(Token::Num(_) | Token::Ident(_) | Token::Sym('('))
+        ) {
+            lhs = Expr::Bin(Op::ImplicitMul, Box::new(lhs), Box::new(self.power()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            Ok(Expr::Bin(Op::Pow, Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Expr::Num(v)),
+            Some(Token::Sym('(')) => {
+                let e = self.expr()?;
+                if self.eat(')') {
+                    Ok(e)
+                } else {
+                    Err("missing )".to_owned())
+                }
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat('(') {
+                    let mut args = Vec::new();
+                    if !self.eat(')') {
+                        loop {
+                            args.push(self.expr()?);
+                            if self.eat(')') {
+                                break;
+                            }
+                            if !self.eat(',') {
+                                return Err(format!("bad arguments to {}", name));
+                            }
+                        }
+                    }
+                    return Ok(Expr::Call(name, args));
+                }
+                let mut path = name;
+                while self.eat('.') {
+                    match self.next() {
+                        Some(Token::Ident(member)) => path = format!("{}.{}", path, member),
+                        _ => return Err(format!("bad member access on {}", path)),
+                    }
+                }
+                Ok(ident_expr(path))
+            }
+            t => Err(format!("unexpected {:?}", t)),
+        }
+    }
+}
+
+// identifiers are units, otherwise constants or project variables. Which units are
+// variables after all is left to Expr::resolve
+fn ident_expr(name: String) -> Expr {
+    if unit_factor(&name).is_some() {
+        Expr::Unit(name)
+    } else {
+        Expr::Var(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(vars: &[(&str, f64)]) -> Scope {
+        Scope {
+            vars: vars.iter().map(|(n, v)| (n.to_string(), *v)).collect(),
+        }
+    }
+
+    fn eval(src: &str, scope: &Scope) -> f64 {
+        Expr::parse_in(src, scope).unwrap().eval(scope).unwrap()
+    }
+
+    #[test]
+    fn units_convert_to_si() {
+        let none = Scope::default();
+        assert!((eval("12 in", &none) - 0.3048).abs() < 1e-12);
+        assert!((eval("180 deg", &none) - PI).abs() < 1e-12);
+        assert!((eval("2 m / 500 ms", &none) - 4.0).abs() < 1e-12);
+        assert!((eval("1 ft + 30 cm", &none) - 0.6048).abs() < 1e-12);
+        assert!((eval("60 RPM", &none) - 2.0 * PI).abs() < 1e-12);
+        assert_eq!(Expr::parse("-1.5 m").unwrap().literal_unit(), Some("m"),);
+    }
+
+    #[test]
+    fn declared_variables_come_before_units() {
+        let vars = scope(&[("A", 2.0), ("s", 3.0)]);
+        assert_eq!(
+            Expr::parse_in("A + 0.5 m", &vars).unwrap().var_refs(),
+            vec!["A"]
+        );
+        assert!((eval("A + 0.5 m", &vars) - 2.5).abs() < 1e-12);
+        assert!((eval("2 s", &vars) - 6.0).abs() < 1e-12);
+        // undeclared, the same names are still units
+        assert!((eval("2 A + 1 V", &scope(&[])) - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unit_names_standing_alone_are_variables_without_a_scope() {
+        let e = Expr::parse("g - 0.3 m").unwrap();
+        assert!(e.has_vars());
+        assert_eq!(e.var_refs(), vec!["g"]);
+        assert!(!Expr::parse("3 g").unwrap().has_vars());
+    }
+}
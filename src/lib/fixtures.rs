@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+// projects for tests, each in a temporary folder of its own
+
+// the deploy folder of an empty pathplanner project named after the test
+pub fn project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pathflip-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let deploy = dir.join("deploy");
+    for folder in ["paths", "autos"] {
+        fs::create_dir_all(deploy.join("pathplanner").join(folder)).unwrap();
+    }
+    fs::write(
+        deploy.join("pathplanner/settings.json"),
+        r#"{"pathFolders": [], "autoFolders": []}"#,
+    )
+    .unwrap();
+    deploy
+}
+
+pub fn remove(deploy: &Path) {
+    fs::remove_dir_all(deploy.parent().unwrap()).unwrap();
+}
+
+// a path through `anchors`, with control points a meter along x on either side
+pub fn path_json(anchors: &[[f64; 2]]) -> Value {
+    let last = anchors.len().saturating_sub(1);
+    let waypoints: Vec<Value> = anchors
+        .iter()
+        .enumerate()
+        .map(|(i, &[x, y])| {
+            let control = |dx: f64, present: bool| present.then(|| json!({ "x": x + dx, "y": y }));
+            json!({
+                "anchor": { "x": x, "y": y },
+                "prevControl": control(-1.0, i > 0),
+                "nextControl": control(1.0, i < last),
+                "isLocked": false,
+                "linkedName": null
+            })
+        })
+        .collect();
+    let constraints = json!({
+        "maxVelocity": 3.0,
+        "maxAcceleration": 3.0,
+        "maxAngularVelocity": 540.0,
+        "maxAngularAcceleration": 720.0,
+        "nominalVoltage": 12.0,
+        "unlimited": false
+    });
+    json!({
+        "version": "2025.0",
+        "waypoints": waypoints,
+        "rotationTargets": [],
+        "constraintZones": [],
+        "pointTowardsZones": [],
+        "eventMarkers": [],
+        "globalConstraints": constraints,
+        "goalEndState": { "velocity": 0.0, "rotation": 0.0 },
+        "reversed": false,
+        "folder": null,
+        "idealStartingState": { "velocity": 0.0, "rotation": 0.0 },
+        "useDefaultConstraints": true
+    })
+}
+
+pub fn write_path(deploy: &Path, name: &str, anchors: &[[f64; 2]]) -> PathBuf {
+    let path = deploy.join(format!("pathplanner/paths/{}.path", name));
+    fs::write(&path, path_json(anchors).to_string()).unwrap();
+    path
+}
+
+// an auto following `paths` in order
+pub fn write_auto(deploy: &Path, name: &str, paths: &[&str]) -> PathBuf {
+    let commands: Vec<Value> = paths
+        .iter()
+        .map(|p| json!({ "type": "path", "data": { "pathName": p } }))
+        .collect();
+    let json = json!({
+        "version": "2025.0",
+        "command": { "type": "sequential", "data": { "commands": commands } },
+        "resetOdom": true,
+        "folder": null,
+        "choreoAuto": false
+    });
+    let auto = deploy.join(format!("pathplanner/autos/{}.auto", name));
+    fs::write(&auto, json.to_string()).unwrap();
+    auto
+}
@@ -1,21 +1,487 @@
+use std::f64::consts::PI;
+
+use crate::lib::calibration::FieldProfile;
+
 pub const FIELD_Y: f64 = 8.07;
 pub const FIELD_X: f64 = 16.54;
+// anything with positions or headings on the field. Flips are presets of Transform2d
 pub trait Flippable {
-    fn flip_alliance(&mut self);
-    fn flip_same_alliance(&mut self);
+    fn transform(&mut self, t: &Transform2d);
+}
+
+// a motion of the field: stretched along x and y, reflected across the x axis or not,
+// then turned by `rotation` about the origin, then moved by `translation`. Headings are
+// mirrored and turned on their own, which for a rigid motion is by the same reflection and
+// rotation. They aren't stretched
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform2d {
+    // 1 for both unless the field is being calibrated
+    pub scale: [f64; 2],
+    // counterclockwise, in radians
+    pub rotation: f64,
+    pub reflect: bool,
+    pub translation: [f64; 2],
+    pub heading_rotation: f64,
+    pub heading_reflect: bool,
+}
+
+// the field flips are made on, in the coordinates its paths are in: the nominal field, or
+// one measured at a venue
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Field {
+    pub length: f64,
+    pub width: f64,
+    // where the field's corner is
+    pub origin: [f64; 2],
+}
+
+impl Field {
+    pub const NOMINAL: Field = Field {
+        length: FIELD_X,
+        width: FIELD_Y,
+        origin: [0.0, 0.0],
+    };
+
+    pub fn center(&self) -> [f64; 2] {
+        [
+            self.origin[0] + self.length / 2.0,
+            self.origin[1] + self.width / 2.0,
+        ]
+    }
+}
+
+// v -> offset + scale * v, for one coordinate a transform maps on its own
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AxisMap {
+    pub offset: f64,
+    pub scale: f64,
+}
+
+impl AxisMap {
+    pub fn apply(self, v: f64) -> f64 {
+        // adding 0.0 turns -0.0 into 0.0, so a negated zero never comes out signed
+        self.offset + self.scale * v + 0.0
+    }
+
+    pub fn is_identity(self) -> bool {
+        self.offset == 0.0 && self.scale == 1.0
+    }
+}
+
+impl Transform2d {
+    pub const IDENTITY: Transform2d = Transform2d {
+        scale: [1.0, 1.0],
+        rotation: 0.0,
+        reflect: false,
+        translation: [0.0, 0.0],
+        heading_rotation: 0.0,
+        heading_reflect: false,
+    };
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self {
+            translation: [x, y],
+            ..Self::IDENTITY
+        }
+    }
+
+    // stretched away from the origin
+    pub fn stretch(x: f64, y: f64) -> Self {
+        Self {
+            scale: [x, y],
+            ..Self::IDENTITY
+        }
+    }
+
+    // counterclockwise about `center`
+    pub fn rotate_about(radians: f64, center: [f64; 2]) -> Self {
+        Self::translate(-center[0], -center[1])
+            .then(Self::turn(wrap(radians), false))
+            .then(Self::translate(center[0], center[1]))
+    }
+
+    // across the line through `point` at `radians` from the x axis
+    pub fn reflect_across(point: [f64; 2], radians: f64) -> Self {
+        Self::translate(-point[0], -point[1])
+            .then(Self::turn(wrap(2.0 * radians), true))
+            .then(Self::translate(point[0], point[1]))
+    }
+
+    // about the origin, with headings turned and mirrored the same way
+    fn turn(rotation: f64, reflect: bool) -> Self {
+        Self {
+            rotation,
+            reflect,
+            heading_rotation: rotation,
+            heading_reflect: reflect,
+            ..Self::IDENTITY
+        }
+    }
+
+    // moving positions the same way, but leaving headings as they were
+    pub fn keeping_headings(self) -> Self {
+        Self {
+            heading_rotation: 0.0,
+            heading_reflect: false,
+            ..self
+        }
+    }
+
+    // across the field's long center line, to the other side of the same alliance
+    pub fn same_alliance(field: &Field) -> Self {
+        Self::reflect_across([0.0, field.center()[1]], 0.0)
+    }
+
+    // across the center line between the alliances. Headings stay as they were, which is
+    // how this flip has always been made
+    pub fn alliance(field: &Field) -> Self {
+        Self::mirrored_alliance(field).keeping_headings()
+    }
+
+    // the alliance flip with headings mirrored across the center line too
+    pub fn mirrored_alliance(field: &Field) -> Self {
+        Self::reflect_across([field.center()[0], 0.0], PI / 2.0)
+    }
+
+    // half a turn about the center of the field
+    pub fn rotational(field: &Field) -> Self {
+        Self::rotate_about(PI, field.center())
+    }
+
+    // this transform followed by `next`. A stretch in `next` only carries through quarter
+    // turns, which are all that calibrating and flipping need
+    pub fn then(self, next: Transform2d) -> Transform2d {
+        let turned = if next.reflect {
+            -self.rotation
+        } else {
+            self.rotation
+        };
+        let heading_turned = if next.heading_reflect {
+            -self.heading_rotation
+        } else {
+            self.heading_rotation
+        };
+        let stretch = if cos_sin(self.rotation).1 == 0.0 {
+            next.scale
+        } else {
+            [next.scale[1], next.scale[0]]
+        };
+        Transform2d {
+            scale: [self.scale[0] * stretch[0], self.scale[1] * stretch[1]],
+            rotation: wrap(next.rotation + turned),
+            reflect: self.reflect != next.reflect,
+            translation: next.point(self.translation),
+            heading_rotation: wrap(next.heading_rotation + heading_turned),
+            heading_reflect: self.heading_reflect != next.heading_reflect,
+        }
+    }
+
+    pub fn point(&self, p: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.vector(p);
+        [x + self.translation[0] + 0.0, y + self.translation[1] + 0.0]
+    }
+
+    // a direction or rate of change of position, which turns but doesn't move
+    pub fn vector(&self, v: [f64; 2]) -> [f64; 2] {
+        let (cos, sin) = cos_sin(self.rotation);
+        let x = v[0] * self.scale[0];
+        let y = v[1] * self.scale[1];
+        let y = if self.reflect { -y } else { y };
+        [cos * x - sin * y + 0.0, sin * x + cos * y + 0.0]
+    }
+
+    // in radians. Left unwrapped so headings along a trajectory stay continuous
+    pub fn heading(&self, heading: f64) -> f64 {
+        self.heading_rotation + self.rate(heading)
+    }
+
+    // in degrees, kept within (-180, 180] the way pathplanner writes them
+    pub fn heading_degrees(&self, degrees: f64) -> f64 {
+        let turned = self.heading_rotation.to_degrees() + self.rate(degrees);
+        if turned > 180.0 {
+            turned - 360.0
+        } else if turned <= -180.0 {
+            turned + 360.0
+        } else {
+            turned
+        }
+    }
 
-    fn flip(&mut self, same_alliance: bool) {
-        if same_alliance {
-            self.flip_same_alliance();
+    // an angular velocity or acceleration, or an angle relative to a heading
+    pub fn rate(&self, rate: f64) -> f64 {
+        if self.heading_reflect {
+            0.0 - rate
         } else {
-            self.flip_alliance();
+            rate
         }
     }
+
+    // how x, y and heading each map when none of them depends on another, which holds for
+    // translations, half turns and reflections across lines parallel to an axis. Choreo
+    // expressions can only follow a transform like that
+    pub fn axis_maps(&self) -> Option<[AxisMap; 3]> {
+        let (cos, sin) = cos_sin(self.rotation);
+        if sin != 0.0 {
+            return None;
+        }
+        let y_scale = if self.reflect { -cos } else { cos };
+        Some([
+            AxisMap {
+                offset: self.translation[0],
+                scale: cos * self.scale[0],
+            },
+            AxisMap {
+                offset: self.translation[1],
+                scale: y_scale * self.scale[1],
+            },
+            AxisMap {
+                offset: self.heading_rotation,
+                scale: if self.heading_reflect { -1.0 } else { 1.0 },
+            },
+        ])
+    }
+}
+
+// the cosine and sine of quarter turns come out exact, so flipping twice gives back the
+// same numbers
+fn cos_sin(radians: f64) -> (f64, f64) {
+    let snap = |v: f64| {
+        if v.abs() < 1e-12 {
+            0.0
+        } else if (v.abs() - 1.0).abs() < 1e-12 {
+            v.signum()
+        } else {
+            v
+        }
+    };
+    (snap(radians.cos()), snap(radians.sin()))
+}
+
+// into (-pi, pi]
+fn wrap(radians: f64) -> f64 {
+    let wrapped = radians.rem_euclid(2.0 * PI);
+    if wrapped > PI {
+        wrapped - 2.0 * PI
+    } else {
+        wrapped + 0.0
+    }
+}
+
+// one step of a custom transform, as entered. Angles are in degrees and lengths in meters
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum TransformStep {
+    Translate { x: f64, y: f64 },
+    // counterclockwise about (x, y)
+    Rotate { degrees: f64, x: f64, y: f64 },
+    // across the line through (x, y) at `degrees` from the x axis
+    Reflect { degrees: f64, x: f64, y: f64 },
+}
+
+impl TransformStep {
+    pub fn transform(&self) -> Transform2d {
+        match *self {
+            TransformStep::Translate { x, y } => Transform2d::translate(x, y),
+            TransformStep::Rotate { degrees, x, y } => {
+                Transform2d::rotate_about(degrees.to_radians(), [x, y])
+            }
+            TransformStep::Reflect { degrees, x, y } => {
+                Transform2d::reflect_across([x, y], degrees.to_radians())
+            }
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FlipOptions {
+    // kept in the manifest as the entry's mode
+    #[serde(skip)]
+    pub same_alliance: bool,
+    // mirror headings across the center line with an alliance flip instead of keeping them
+    pub mirror_headings: bool,
+    // applied in order instead of a same alliance or alliance flip when there are any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom_transform: Vec<TransformStep>,
+    // neither flipped nor moved, for a path that's only reversed. Kept in the manifest too
+    #[serde(skip)]
+    pub in_place: bool,
+    // time stretch applied to choreo trajectories, 1.0 leaves them untouched
+    pub time_scale: f64,
+    pub resample_dt: Option<f64>,
+    // add mirrored copies of the choreo project variables the waypoints reference
+    pub mirror_variables: bool,
+    pub precision: Precision,
+    // write files in the current schema instead of the version they were read in
+    pub write_latest: bool,
+    // run the path or trajectory end to start, for the way back along it
+    pub reverse: bool,
+    // the venue the project is calibrated to, from its manifest rather than the entry
+    #[serde(skip)]
+    pub calibration: Option<FieldProfile>,
 }
 
-pub fn flip_xaxis(x: f64, y: f64) -> [f64; 2] {
-    [x, FIELD_Y - y]
+impl FlipOptions {
+    pub fn field(&self) -> Field {
+        self.calibration
+            .as_ref()
+            .map_or(Field::NOMINAL, FieldProfile::field)
+    }
+
+    pub fn transform(&self) -> Transform2d {
+        if !self.custom_transform.is_empty() {
+            self.custom_transform
+                .iter()
+                .fold(Transform2d::IDENTITY, |t, step| t.then(step.transform()))
+        } else if self.in_place {
+            Transform2d::IDENTITY
+        } else if self.same_alliance {
+            Transform2d::same_alliance(&self.field())
+        } else if self.mirror_headings {
+            Transform2d::mirrored_alliance(&self.field())
+        } else {
+            Transform2d::alliance(&self.field())
+        }
+    }
 }
-pub fn flip_yaxis(x: f64, y: f64) -> [f64; 2] {
-    [FIELD_X - x, y]
+
+// rounding steps applied to everything written out, lengths in m and angles in rad
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Precision {
+    pub length: f64,
+    pub angle: f64,
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Self {
+            length: 1e-9,
+            angle: 1e-9,
+        }
+    }
+}
+
+impl Default for FlipOptions {
+    fn default() -> Self {
+        Self {
+            same_alliance: true,
+            custom_transform: Vec::new(),
+            mirror_headings: false,
+            in_place: false,
+            time_scale: 1.0,
+            resample_dt: None,
+            mirror_variables: true,
+            precision: Precision::default(),
+            write_latest: false,
+            reverse: false,
+            calibration: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::{Field, Transform2d, FIELD_X, FIELD_Y};
+
+    const POINTS: [[f64; 2]; 3] = [[1.25, 2.5], [0.0, 0.0], [15.0, 7.5]];
+    const HEADINGS: [f64; 4] = [0.0, 0.5, -2.0, PI / 2.0];
+
+    fn close(a: [f64; 2], b: [f64; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9
+    }
+
+    fn same(a: Transform2d, b: Transform2d) -> bool {
+        a.scale == b.scale
+            && (a.rotation - b.rotation).abs() < 1e-12
+            && a.reflect == b.reflect
+            && close(a.translation, b.translation)
+            && (a.heading_rotation - b.heading_rotation).abs() < 1e-12
+            && a.heading_reflect == b.heading_reflect
+    }
+
+    // the flips before transforms: y -> FIELD_Y - y with vy, headings and rates negated
+    #[test]
+    fn same_alliance_matches_the_old_flip() {
+        let t = Transform2d::same_alliance(&Field::NOMINAL);
+        for [x, y] in POINTS {
+            assert!(close(t.point([x, y]), [x, FIELD_Y - y]));
+            assert!(close(t.vector([x, y]), [x, -y]));
+        }
+        for h in HEADINGS {
+            assert!((t.heading(h) + h).abs() < 1e-12);
+            assert_eq!(t.rate(h), 0.0 - h);
+        }
+        assert_eq!(t.heading_degrees(90.0), -90.0);
+    }
+
+    // x -> FIELD_X - x with vx negated, headings and rates as they were
+    #[test]
+    fn alliance_matches_the_old_flip() {
+        let t = Transform2d::alliance(&Field::NOMINAL);
+        for [x, y] in POINTS {
+            assert!(close(t.point([x, y]), [FIELD_X - x, y]));
+            assert!(close(t.vector([x, y]), [-x, y]));
+        }
+        for h in HEADINGS {
+            assert_eq!(t.heading(h), h);
+            assert_eq!(t.rate(h), h);
+        }
+        assert_eq!(t.heading_degrees(30.0), 30.0);
+        let [_, _, heading] = t.axis_maps().unwrap();
+        assert!(heading.is_identity());
+    }
+
+    #[test]
+    fn mirrored_alliance_mirrors_headings() {
+        let t = Transform2d::mirrored_alliance(&Field::NOMINAL);
+        for p in POINTS {
+            assert_eq!(t.point(p), Transform2d::alliance(&Field::NOMINAL).point(p));
+        }
+        // facing +x, toward the other alliance, turns to face -x
+        assert!((t.heading(0.0) - PI).abs() < 1e-12);
+        assert_eq!(t.heading_degrees(30.0), 150.0);
+        assert_eq!(t.rate(1.0), -1.0);
+    }
+
+    #[test]
+    fn flips_compose() {
+        let field = Field::NOMINAL;
+        let same_alliance = Transform2d::same_alliance(&field);
+        let alliance = Transform2d::alliance(&field);
+        let mirrored = Transform2d::mirrored_alliance(&field);
+        let rotational = Transform2d::rotational(&field);
+        assert!(same(same_alliance.then(mirrored), rotational));
+        assert!(same(mirrored.then(same_alliance), rotational));
+        for t in [same_alliance, alliance, mirrored] {
+            assert!(same(t.then(t), Transform2d::IDENTITY));
+        }
+
+        // positions go where the half turn puts them, headings only flip with the first
+        let both = same_alliance.then(alliance);
+        for p in POINTS {
+            assert!(close(both.point(p), rotational.point(p)));
+            assert!(close(both.point(p), alliance.point(same_alliance.point(p))));
+            assert!(close(
+                both.vector(p),
+                alliance.vector(same_alliance.vector(p))
+            ));
+        }
+        for h in HEADINGS {
+            let twice = alliance.heading(same_alliance.heading(h));
+            assert!((both.heading(h) - twice).abs() < 1e-12);
+            assert!((both.heading(h) + h).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn composition_applies_in_order() {
+        let shift = Transform2d::translate(0.5, 0.0);
+        let turn = Transform2d::rotate_about(PI / 2.0, [0.0, 0.0]);
+        // shifted along x, then turned onto y
+        assert!(close(shift.then(turn).point([1.0, 0.0]), [0.0, 1.5]));
+        // turned onto y, then shifted along x
+        assert!(close(turn.then(shift).point([1.0, 0.0]), [0.5, 1.0]));
+    }
 }
@@ -0,0 +1,336 @@
+use serde_json::Value;
+
+// writers that reproduce the files each tool saves itself, so opening and saving a flipped
+// file in choreo or pathplanner leaves it unchanged. Keys keep their order (serde_json is
+// built with preserve_order), doubles read back exactly (float_roundtrip) and strings and
+// keys go through serde_json's escaping
+
+// choreo (2025+, every .traj/.chor version) is written by serde_json from rust: one space
+// indent, no space after ':', objects inside arrays on one line and primitive arrays
+// without spaces. Numbers use serde_json's own float formatting
+pub fn choreo_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_choreo(&mut out, value, false, 0);
+    out
+}
+
+fn write_choreo(out: &mut String, value: &Value, in_array: bool, indent: usize) {
+    match value {
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Object(map) if in_array => {
+            out.push('{');
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_key(out, k, ":");
+                write_choreo(out, v, true, indent);
+            }
+            out.push('}');
+        }
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&" ".repeat(indent + 1));
+                write_key(out, k, ":");
+                write_choreo(out, v, false, indent + 1);
+            }
+            out.push('\n');
+            out.push_str(&" ".repeat(indent));
+            out.push('}');
+        }
+        Value::Array(arr) if in_array || arr.iter().all(is_primitive) => {
+            out.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_choreo(out, v, true, indent);
+            }
+            out.push(']');
+        }
+        Value::Array(arr) => {
+            out.push_str("[\n");
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&"  ".repeat(indent));
+                write_choreo(out, v, true, indent + 1);
+            }
+            out.push(']');
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+// pathplanner (every .path/.auto version and settings.json) is written by dart's
+// JsonEncoder.withIndent('  '), which also decides how doubles are printed
+pub fn pathplanner_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_pathplanner(&mut out, value, 0);
+    out
+}
+
+fn write_pathplanner(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Array(arr) if arr.is_empty() => out.push_str("[]"),
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&"  ".repeat(indent + 1));
+                write_key(out, k, ": ");
+                write_pathplanner(out, v, indent + 1);
+            }
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent));
+            out.push('}');
+        }
+        Value::Array(arr) => {
+            out.push_str("[\n");
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&"  ".repeat(indent + 1));
+                write_pathplanner(out, v, indent + 1);
+            }
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent));
+            out.push(']');
+        }
+        Value::Number(n) if n.is_f64() => {
+            out.push_str(&dart_double(n.as_f64().unwrap_or_default()))
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+fn write_key(out: &mut String, key: &str, separator: &str) {
+    out.push_str(&Value::String(key.to_owned()).to_string());
+    out.push_str(separator);
+}
+
+fn is_primitive(value: &Value) -> bool {
+    !matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+// dart's double.toString: shortest round trip digits, always a decimal point, exponent
+// form below 1e-6 and from 1e21 up with an explicit '+' ("1e-7", "1.5e+21")
+fn dart_double(v: f64) -> String {
+    let abs = v.abs();
+    if abs != 0.0 && !(1e-6..1e21).contains(&abs) {
+        let s = format!("{:e}", v);
+        match s.split_once('e') {
+            Some((mantissa, exp)) if !exp.starts_with('-') => format!("{}e+{}", mantissa, exp),
+            _ => s,
+        }
+    } else {
+        let s = v.to_string();
+        if s.contains('.') {
+            s
+        } else {
+            s + ".0"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a trajectory as choreo saves it, with events and a sample force array
+    const TRAJ: &str = r#"{
+ "name":"Score",
+ "version":1,
+ "snapshot":{
+  "waypoints":[
+    {"x":2.0, "y":7.0, "heading":0.0, "intervals":40, "split":false, "fixTranslation":true, "fixHeading":true, "overrideIntervals":false},
+    {"x":5.75, "y":6.1, "heading":-1.0303768814651526, "intervals":40, "split":false, "fixTranslation":true, "fixHeading":false, "overrideIntervals":false}],
+  "constraints":[
+    {"from":"first", "to":null, "data":{"type":"StopPoint", "props":{}}, "enabled":true},
+    {"from":"last", "to":null, "data":{"type":"StopPoint", "props":{}}, "enabled":true}],
+  "targetDt":0.05
+ },
+ "params":{
+  "waypoints":[
+    {"x":{"exp":"2 m", "val":2.0}, "y":{"exp":"7 m", "val":7.0}, "heading":{"exp":"0 deg", "val":0.0}, "intervals":40, "split":false, "fixTranslation":true, "fixHeading":true, "overrideIntervals":false},
+    {"x":{"exp":"ReefX - 1 m", "val":5.75}, "y":{"exp":"6.1 m", "val":6.1}, "heading":{"exp":"-59.03589 deg", "val":-1.0303768814651526}, "intervals":40, "split":false, "fixTranslation":true, "fixHeading":false, "overrideIntervals":false}],
+  "constraints":[
+    {"from":"first", "to":null, "data":{"type":"StopPoint", "props":{}}, "enabled":true},
+    {"from":"last", "to":null, "data":{"type":"StopPoint", "props":{}}, "enabled":true}],
+  "targetDt":{
+   "exp":"0.05 s",
+   "val":0.05
+  }
+ },
+ "trajectory":{
+  "sampleType":"Swerve",
+  "waypoints":[0.0,1.2],
+  "samples":[
+    {"t":0.0, "x":2.0, "y":7.0, "heading":0.0, "vx":0.0, "vy":0.0, "omega":0.0, "ax":4.1, "ay":-1e-17, "alpha":0.0, "fx":[18.5,18.5,18.5,18.5], "fy":[0.0,0.0,0.0,0.0]},
+    {"t":1.2, "x":5.75, "y":6.1, "heading":-1.0303768814651526, "vx":0.0, "vy":0.0, "omega":0.0, "ax":0.0, "ay":0.0, "alpha":0.0, "fx":[0.0,0.0,0.0,0.0], "fy":[0.0,0.0,0.0,0.0]}],
+  "splits":[0]
+ },
+ "events":[
+  {"name":"Intake", "from":{"target":0, "targetTimestamp":0.0, "offset":{"exp":"0.2 s", "val":0.2}}, "event":{"type":"named", "data":{"name":"intake"}}}]
+}"#;
+
+    // a project file, whose nested objects are never on one line
+    const CHOR: &str = r#"{
+ "name":"Robot",
+ "version":1,
+ "type":"Swerve",
+ "variables":{
+  "expressions":{
+   "ReefX":{
+    "dimension":"Length",
+    "var":{
+     "exp":"6.75 m",
+     "val":6.75
+    }
+   }
+  },
+  "poses":{}
+ },
+ "config":{
+  "mass":{
+   "exp":"150 lbs",
+   "val":68.0388555
+  },
+  "bumper":{
+   "front":{
+    "exp":"16 in",
+    "val":0.4064
+   }
+  }
+ },
+ "generationFeatures":[]
+}"#;
+
+    // a path as pathplanner saves it, with dart's integers and doubles side by side
+    const PATH: &str = r#"{
+  "version": "2025.0",
+  "waypoints": [
+    {
+      "anchor": {
+        "x": 2.0,
+        "y": 7.0
+      },
+      "prevControl": null,
+      "nextControl": {
+        "x": 3.0,
+        "y": 7.0
+      },
+      "isLocked": false,
+      "linkedName": null
+    },
+    {
+      "anchor": {
+        "x": 5.75,
+        "y": 6.1
+      },
+      "prevControl": {
+        "x": 4.75,
+        "y": 6.1000000000000005
+      },
+      "nextControl": null,
+      "isLocked": false,
+      "linkedName": "Reef"
+    }
+  ],
+  "rotationTargets": [],
+  "constraintZones": [],
+  "pointTowardsZones": [],
+  "eventMarkers": [
+    {
+      "name": "Intake",
+      "waypointRelativePos": 0.5,
+      "endWaypointRelativePos": null,
+      "command": {
+        "type": "named",
+        "data": {
+          "name": "intake"
+        }
+      }
+    }
+  ],
+  "globalConstraints": {
+    "maxVelocity": 3.0,
+    "maxAcceleration": 3.0,
+    "maxAngularVelocity": 540.0,
+    "maxAngularAcceleration": 720.0,
+    "nominalVoltage": 12.0,
+    "unlimited": false
+  },
+  "goalEndState": {
+    "velocity": 0,
+    "rotation": -1.1102230246251565e-16
+  },
+  "reversed": false,
+  "folder": null,
+  "idealStartingState": {
+    "velocity": 0,
+    "rotation": 0.0
+  },
+  "useDefaultConstraints": true
+}"#;
+
+    const AUTO: &str = r#"{
+  "version": "2025.0",
+  "command": {
+    "type": "sequential",
+    "data": {
+      "commands": [
+        {
+          "type": "path",
+          "data": {
+            "pathName": "Score"
+          }
+        },
+        {
+          "type": "parallel",
+          "data": {
+            "commands": []
+          }
+        }
+      ]
+    }
+  },
+  "resetOdom": true,
+  "folder": null,
+  "choreoAuto": false
+}"#;
+
+    fn round_trip(text: &str, write: fn(&Value) -> String) {
+        let value: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(write(&value), text);
+    }
+
+    #[test]
+    fn choreo_files_are_written_back_unchanged() {
+        round_trip(TRAJ, choreo_string);
+        round_trip(CHOR, choreo_string);
+    }
+
+    #[test]
+    fn pathplanner_files_are_written_back_unchanged() {
+        round_trip(PATH, pathplanner_string);
+        round_trip(AUTO, pathplanner_string);
+    }
+
+    #[test]
+    fn doubles_are_printed_as_dart_does() {
+        assert_eq!(dart_double(3.0), "3.0");
+        assert_eq!(dart_double(-0.5), "-0.5");
+        assert_eq!(dart_double(1e-7), "1e-7");
+        assert_eq!(dart_double(1.5e21), "1.5e+21");
+    }
+}
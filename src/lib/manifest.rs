@@ -0,0 +1,516 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::{
+    lib::{
+        calibration::FieldProfile,
+        error::{Error, Result},
+        flip::FlipOptions,
+        plan::WritePlan,
+        plot::{DualPlotter, Plotter},
+        project,
+        variants::{self, NAME_PLACEHOLDER},
+    },
+    FlipFileType,
+};
+
+// .pathflip.json in the deploy folder records which file every flipped output came from
+// and how, so outputs can be rebuilt when their sources change. Paths are relative to the
+// manifest's folder and always use '/', so the manifest can be committed with the robot
+// code and used from any OS
+
+pub const MANIFEST_NAME: &str = ".pathflip.json";
+const MANIFEST_VERSION: i64 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub version: i64,
+    // the venue the project is calibrated to, the nominal field when None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<FieldProfile>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub source: String,
+    pub output: String,
+    // the paths or trajectories of an auto, flipped with it, each once and sorted by file
+    // name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<PathPair>,
+    // how outputs were named from their sources, like "{name}_Mirrored", when they all
+    // were the same way. Paths added to an auto later are named by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub naming: Option<String>,
+    pub mode: FlipMode,
+    pub options: FlipOptions,
+    // of the source, and of each of an auto's paths after it
+    pub source_hash: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PathPair {
+    pub source: String,
+    pub output: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FlipMode {
+    SameAlliance,
+    OtherAlliance,
+    // the options' custom transform
+    Custom,
+    // left where it was and only reversed
+    InPlace,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            field: None,
+            entries: Vec::new(),
+        }
+    }
+}
+
+// the venue the project `file` is in is calibrated to
+pub fn calibration(file: &Path) -> Result<Option<FieldProfile>> {
+    Ok(Manifest::load(&manifest_path(file), &WritePlan::default())?.field)
+}
+
+// the manifest for the project `file` is in: in the deploy folder, or next to `file` when
+// there isn't one. Flips are kept in their source's
+pub fn manifest_path(file: &Path) -> PathBuf {
+    match project::Layout::discover(file).deploy {
+        Some(deploy) => deploy.join(MANIFEST_NAME),
+        None => file.with_file_name(MANIFEST_NAME),
+    }
+}
+
+impl Entry {
+    // the options the entry was flipped with, the mode included
+    pub fn options(&self) -> FlipOptions {
+        let mut opts = self.options.clone();
+        opts.same_alliance = self.mode == FlipMode::SameAlliance;
+        opts.in_place = self.mode == FlipMode::InPlace;
+        opts
+    }
+
+    // the auto's paths as it follows them now, each with where it's flipped to. Those added
+    // since the auto was flipped are named by the entry's rule, next to the other outputs
+    fn path_pairs(&self, root: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let recorded: Vec<(PathBuf, PathBuf)> = self
+            .paths
+            .iter()
+            .map(|p| (root.join(&p.source), root.join(&p.output)))
+            .collect();
+        let Some(rule) = &self.naming else {
+            return Ok(recorded);
+        };
+        let output_dir = recorded.first().and_then(|(_, output)| output.parent());
+        variants::auto_paths(&root.join(&self.source))?
+            .into_iter()
+            .map(|path| {
+                let absolute = project::absolute(&path);
+                if let Some(pair) = recorded
+                    .iter()
+                    .find(|(source, _)| project::absolute(source) == absolute)
+                {
+                    return Ok(pair.clone());
+                }
+                let dir = output_dir.or(path.parent()).unwrap_or(Path::new(""));
+                let name = variants::apply_rule(rule, &stem(&path));
+                let ext = path.extension().unwrap_or_default().to_string_lossy();
+                let output = dir.join(format!("{}.{}", name, ext));
+                Ok((path, output))
+            })
+            .collect()
+    }
+}
+
+impl Manifest {
+    // a missing manifest is an empty one
+    pub fn load(path: &Path, plan: &WritePlan) -> Result<Manifest> {
+        let text = match plan.read(path) {
+            Ok(text) => text,
+            Err(_) if !path.exists() => return Ok(Manifest::default()),
+            Err(err) => return Err(err),
+        };
+        let manifest: Manifest =
+            serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+                .map_err(|e| Error::parse(path, e))?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(Error::schema(
+                path,
+                format!(
+                    "manifest version {} is newer than the supported version {}",
+                    manifest.version, MANIFEST_VERSION
+                ),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path, plan: &mut WritePlan) -> Result<()> {
+        let json = serde_json::to_value(self).unwrap_or_default();
+        let text = crate::lib::format::pathplanner_string(&json);
+        if path.exists() {
+            plan.modify(path, text)
+        } else {
+            plan.output(path, text);
+            Ok(())
+        }
+    }
+
+    // every file an output is flipped from
+    pub fn sources(&self, root: &Path) -> Vec<PathBuf> {
+        let mut sources: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .flat_map(|e| std::iter::once(&e.source).chain(e.paths.iter().map(|p| &p.source)))
+            .map(|source| root.join(source))
+            .collect();
+        sources.sort();
+        sources.dedup();
+        sources
+    }
+
+    // every flipped file
+    pub fn outputs(&self, root: &Path) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .flat_map(|e| std::iter::once(&e.output).chain(e.paths.iter().map(|p| &p.output)))
+            .map(|output| root.join(output))
+            .collect()
+    }
+
+    // adds a flip, replacing whatever made the same output before
+    pub fn record(&mut self, entry: Entry) {
+        self.entries.retain(|e| e.output != entry.output);
+        self.entries.push(entry);
+    }
+
+    // flips every entry whose source changed or whose output is missing into `plan`, and
+    // returns the outputs rebuilt
+    fn regenerate(&mut self, root: &Path, plan: &mut WritePlan) -> Result<Vec<PathBuf>> {
+        let mut rebuilt = Vec::new();
+        for entry in &mut self.entries {
+            let source = root.join(&entry.source);
+            let output = root.join(&entry.output);
+            let paths = entry.path_pairs(root)?;
+            let hash = source_hash(&source, &paths, plan)?;
+            let outputs_exist = output.exists() && paths.iter().all(|(_, output)| output.exists());
+            if hash == entry.source_hash && outputs_exist {
+                continue;
+            }
+            plan_flip(&source, &output, &paths, &entry.options(), plan)?;
+            entry.paths = path_pairs(root, &paths);
+            entry.source_hash = hash;
+            rebuilt.push(output);
+        }
+        Ok(rebuilt)
+    }
+}
+
+// rebuilds the outputs of the manifest at `path` whose sources changed, through `plan`
+pub fn regenerate(path: &Path, plan: &mut WritePlan) -> Result<Vec<PathBuf>> {
+    check_exists(path)?;
+    let mut manifest = Manifest::load(path, plan)?;
+    let rebuilt = manifest.regenerate(path.parent().unwrap_or(Path::new("")), plan)?;
+    if !rebuilt.is_empty() {
+        manifest.save(path, plan)?;
+    }
+    Ok(rebuilt)
+}
+
+pub fn check_exists(path: &Path) -> Result<()> {
+    if path.exists() {
+        Ok(())
+    } else {
+        Err(Error::layout(
+            path,
+            "no manifest here, nothing in this project has been flipped yet",
+        ))
+    }
+}
+
+// the file type of a flip, from its source and, for autos, its paths
+fn file_type(source: &Path, paths: &[(PathBuf, PathBuf)]) -> Result<FlipFileType> {
+    let ext = |p: &Path| {
+        p.extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    match ext(source).as_str() {
+        "traj" => Ok(FlipFileType::Choreo),
+        "path" => Ok(FlipFileType::Pathplanner),
+        "auto" => Ok(FlipFileType::PathplannerAuto {
+            is_chor: paths.first().is_some_and(|(p, _)| ext(p) == "traj"),
+        }),
+        _ => Err(Error::layout(source, "not a .traj, .path or .auto file")),
+    }
+}
+
+pub fn plan_flip(
+    source: &Path,
+    output: &Path,
+    paths: &[(PathBuf, PathBuf)],
+    opts: &FlipOptions,
+    plan: &mut WritePlan,
+) -> Result<()> {
+    // flipped about the centre of the venue the project is calibrated to
+    let opts = FlipOptions {
+        calibration: Manifest::load(&manifest_path(source), plan)?.field,
+        ..opts.clone()
+    };
+    let mut plotter = DualPlotter::default();
+    plotter.set_plot_type(
+        &file_type(source, paths)?,
+        paths.iter().map(|(source, _)| source.clone()).collect(),
+    );
+    let outputs: Vec<PathBuf> = paths.iter().map(|(_, output)| output.clone()).collect();
+    plotter.plan_flip(
+        source.display().to_string(),
+        output,
+        &opts,
+        Some(&outputs),
+        plan,
+    )
+}
+
+// records a flip already in `plan` in the manifest of its project, through the same plan
+pub fn record_flip(
+    source: &Path,
+    output: &Path,
+    paths: &[(PathBuf, PathBuf)],
+    opts: &FlipOptions,
+    plan: &mut WritePlan,
+) -> Result<()> {
+    // the same manifest plan_flip takes the calibration from
+    let manifest_path = manifest_path(source);
+    let root = manifest_path.parent().unwrap_or(Path::new(""));
+    let mut manifest = Manifest::load(&manifest_path, plan)?;
+    manifest.record(Entry {
+        source: relative(root, source),
+        output: relative(root, output),
+        paths: path_pairs(root, paths),
+        naming: if paths.is_empty() {
+            naming_rule(&[(source.to_path_buf(), output.to_path_buf())])
+        } else {
+            naming_rule(paths)
+        },
+        mode: if !opts.custom_transform.is_empty() {
+            FlipMode::Custom
+        } else if opts.in_place {
+            FlipMode::InPlace
+        } else if opts.same_alliance {
+            FlipMode::SameAlliance
+        } else {
+            FlipMode::OtherAlliance
+        },
+        options: opts.clone(),
+        source_hash: source_hash(source, paths, plan)?,
+    });
+    manifest.save(&manifest_path, plan)
+}
+
+fn path_pairs(root: &Path, paths: &[(PathBuf, PathBuf)]) -> Vec<PathPair> {
+    paths
+        .iter()
+        .map(|(source, output)| PathPair {
+            source: relative(root, source),
+            output: relative(root, output),
+        })
+        .collect()
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+// the rule naming every output after its source, when the source's name is in each of
+// them once and around it the same way
+fn naming_rule(pairs: &[(PathBuf, PathBuf)]) -> Option<String> {
+    let rules: Vec<Option<String>> = pairs
+        .iter()
+        .map(|(source, output)| {
+            let (source, output) = (stem(source), stem(output));
+            (source != output && output.matches(&source).count() == 1)
+                .then(|| output.replacen(&source, NAME_PLACEHOLDER, 1))
+        })
+        .collect();
+    let first = rules.first()?.clone()?;
+    rules
+        .iter()
+        .all(|rule| rule.as_ref() == Some(&first))
+        .then_some(first)
+}
+
+// `path` from `root` with '/' separators, or absolute when it's outside of `root`
+fn relative(root: &Path, path: &Path) -> String {
+    let path = project::absolute(path);
+    match path.strip_prefix(project::absolute(root)) {
+        Ok(rel) => rel
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+// as planned, so sources calibrated in the same plan hash as they'll be written
+fn source_hash(source: &Path, paths: &[(PathBuf, PathBuf)], plan: &WritePlan) -> Result<String> {
+    let mut hash = FNV_OFFSET;
+    for file in std::iter::once(source).chain(paths.iter().map(|(source, _)| source.as_path())) {
+        hash = fnv1a(hash, plan.read(file)?.as_bytes());
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+// 64 bit FNV-1a, enough to notice a file changed without pulling in a hashing crate
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::lib::fixtures::{self, project, write_auto, write_path};
+
+    fn flip(source: &Path, output: &Path, paths: &[(PathBuf, PathBuf)]) {
+        let mut plan = WritePlan::default();
+        let opts = FlipOptions::default();
+        plan_flip(source, output, paths, &opts, &mut plan).unwrap();
+        record_flip(source, output, paths, &opts, &mut plan).unwrap();
+        plan.commit().unwrap();
+    }
+
+    fn names(files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn only_outputs_of_changed_sources_are_rebuilt() {
+        let deploy = project("regen");
+        let a = write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        let b = write_path(&deploy, "B", &[[2.0, 1.0], [4.0, 1.0]]);
+        let a_out = a.with_file_name("A_Mirrored.path");
+        flip(&a, &a_out, &[]);
+        flip(&b, &b.with_file_name("B_Mirrored.path"), &[]);
+        let manifest = deploy.join(MANIFEST_NAME);
+        assert!(regenerate(&manifest, &mut WritePlan::default())
+            .unwrap()
+            .is_empty());
+
+        write_path(&deploy, "A", &[[1.5, 1.0], [3.5, 1.0]]);
+        let mut plan = WritePlan::default();
+        let rebuilt = regenerate(&manifest, &mut plan).unwrap();
+        assert_eq!(names(&rebuilt), vec!["A_Mirrored.path"]);
+        let before = fs::read_to_string(&a_out).unwrap();
+        plan.commit().unwrap();
+        assert_ne!(fs::read_to_string(&a_out).unwrap(), before);
+        assert!(regenerate(&manifest, &mut WritePlan::default())
+            .unwrap()
+            .is_empty());
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn paths_added_to_an_auto_are_named_by_its_rule() {
+        let deploy = project("naming");
+        let a = write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        write_path(&deploy, "B", &[[2.0, 1.0], [4.0, 1.0]]);
+        let auto = write_auto(&deploy, "Auto", &["A"]);
+        let auto_out = auto.with_file_name("Auto_Mirrored.auto");
+        flip(
+            &auto,
+            &auto_out,
+            &[(a.clone(), a.with_file_name("A_Mirrored.path"))],
+        );
+
+        let manifest_path = deploy.join(MANIFEST_NAME);
+        let manifest = Manifest::load(&manifest_path, &WritePlan::default()).unwrap();
+        assert_eq!(
+            manifest.entries[0].naming.as_deref(),
+            Some("{name}_Mirrored")
+        );
+
+        write_auto(&deploy, "Auto", &["A", "B"]);
+        let mut plan = WritePlan::default();
+        let rebuilt = regenerate(&manifest_path, &mut plan).unwrap();
+        assert_eq!(names(&rebuilt), vec!["Auto_Mirrored.auto"]);
+        plan.commit().unwrap();
+        assert!(deploy.join("pathplanner/paths/B_Mirrored.path").is_file());
+        let flipped = fs::read_to_string(&auto_out).unwrap();
+        assert!(flipped.contains("\"B_Mirrored\""), "{}", flipped);
+        let manifest = Manifest::load(&manifest_path, &WritePlan::default()).unwrap();
+        let outputs: Vec<&str> = manifest.entries[0]
+            .paths
+            .iter()
+            .map(|p| p.output.as_str())
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                "pathplanner/paths/A_Mirrored.path",
+                "pathplanner/paths/B_Mirrored.path"
+            ]
+        );
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn outputs_named_differently_have_no_rule() {
+        let pair = |source: &str, output: &str| (PathBuf::from(source), PathBuf::from(output));
+        assert_eq!(
+            naming_rule(&[pair("a/Left.path", "b/Left_Red.path")]).as_deref(),
+            Some("{name}_Red")
+        );
+        assert_eq!(naming_rule(&[pair("Left.path", "Right.path")]), None);
+        assert_eq!(
+            naming_rule(&[pair("A.path", "A_Red.path"), pair("B.path", "Red_B.path")]),
+            None
+        );
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn paths_outside_the_root_stay_absolute() {
+        let root = project::absolute(Path::new("project/deploy"));
+        assert_eq!(
+            relative(&root, &root.join("pathplanner").join("A.path")),
+            "pathplanner/A.path"
+        );
+        let outside = project::absolute(Path::new("elsewhere/A.path"));
+        assert_eq!(relative(&root, &outside), outside.display().to_string());
+    }
+}
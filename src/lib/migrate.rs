@@ -0,0 +1,496 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::lib::{
+    chor::ChoreoData,
+    error::{Error, Result, WithPath},
+    pathplanner::{auto::AutoData, path::PathData},
+    plan::WritePlan,
+};
+
+// files are read into a serde_json::Value first so schemas from older releases can be
+// upgraded to the current model, and written back in the version they came in
+
+pub const CHOREO_VERSION: i64 = 1;
+pub const PATHPLANNER_VERSION: &str = "2025.0";
+
+pub trait Versioned: Serialize + DeserializeOwned {
+    // upgrades json in place to the current schema, or explains why it can't
+    fn upgrade(json: &mut Value) -> std::result::Result<(), String>;
+
+    fn is_current(json: &Value) -> bool;
+
+    // removes what the model kept of an older version that the current one doesn't have
+    fn drop_legacy(_json: &mut Value) {}
+
+    // the current schema written in the layout of `source`. `model` is the source as the
+    // model read it
+    fn downgrade(json: Value, source: &Value, model: &Value) -> Value {
+        let mut out = downgrade_like(json, source, model);
+        if let (Some(obj), Some(version)) = (out.as_object_mut(), source.get("version")) {
+            obj.insert("version".to_owned(), version.clone());
+        }
+        out
+    }
+}
+
+pub struct Loaded<T> {
+    pub data: T,
+    // the file as it was read, before upgrading
+    pub source: Value,
+    // `data` as it was read, serialized, which has every key the model owns. Null for
+    // files in the current version, which aren't downgraded
+    pub model: Value,
+}
+
+impl<T: Versioned> Loaded<T> {
+    pub fn is_current(&self) -> bool {
+        T::is_current(&self.source)
+    }
+
+    // the json to write, in the source's version unless `latest` is set
+    pub fn to_value(&self, latest: bool) -> Value {
+        // the models only hold strings, numbers and string keyed maps, which always
+        // serialize
+        let mut json = serde_json::to_value(&self.data).unwrap_or_default();
+        if self.is_current() {
+            json
+        } else if latest {
+            T::drop_legacy(&mut json);
+            json
+        } else {
+            T::downgrade(json, &self.source, &self.model)
+        }
+    }
+}
+
+// `path` is only used to report errors
+pub fn from_reader<T: Versioned>(mut reader: impl Read, path: &Path) -> Result<Loaded<T>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).with_path(path)?;
+    let source: Value =
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+            .map_err(|e| Error::parse(path, e))?;
+    if T::is_current(&source) {
+        // straight from the text so errors point at a line and column
+        let data = serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+            .map_err(|e| Error::parse(path, e))?;
+        return Ok(Loaded {
+            data,
+            source,
+            model: Value::Null,
+        });
+    }
+    let mut json = source.clone();
+    T::upgrade(&mut json).map_err(|msg| Error::schema(path, msg))?;
+    let data: T = serde_path_to_error::deserialize(json).map_err(|e| Error::parse(path, e))?;
+    let model = serde_json::to_value(&data).unwrap_or_default();
+    Ok(Loaded {
+        data,
+        source,
+        model,
+    })
+}
+
+pub fn load<T: Versioned>(path: impl AsRef<Path>) -> Result<Loaded<T>> {
+    let path = path.as_ref();
+    from_reader(BufReader::new(File::open(path).with_path(path)?), path)
+}
+
+// `path` as it will be once `plan` is written
+pub fn load_planned<T: Versioned>(path: &Path, plan: &WritePlan) -> Result<Loaded<T>> {
+    from_reader(plan.read(path)?.as_bytes(), path)
+}
+
+// keys and key order come from source. A key the model doesn't own, because the upgrade
+// dropped it or the model doesn't know it, is copied from source; one it owns but left
+// out stays out, and so does a key only newer schemas have
+fn downgrade_like(json: Value, source: &Value, model: &Value) -> Value {
+    match (json, source) {
+        (Value::Object(mut map), Value::Object(src)) => Value::Object(
+            src.iter()
+                .filter_map(|(k, s)| match map.remove(k) {
+                    Some(v) => Some((k.clone(), downgrade_like(v, s, &model[k]))),
+                    None if model.get(k).is_some() => None,
+                    None => Some((k.clone(), s.clone())),
+                })
+                .collect(),
+        ),
+        (Value::Array(arr), Value::Array(src)) if !src.is_empty() => Value::Array(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, v)| downgrade_element(v, src, i))
+                .collect(),
+        ),
+        (json, _) => json,
+    }
+}
+
+// flipping, reversing and resampling move array elements around, so an element can't
+// take anything from the source element in its place. The models keep every field of
+// an element on it, and it only drops the keys no element of `src` has, taking its key
+// order from the one at `i`
+fn downgrade_element(json: Value, src: &[Value], i: usize) -> Value {
+    let template = src.get(i).unwrap_or(&src[src.len() - 1]);
+    let Value::Object(mut map) = json else {
+        return downgrade_like(json, template, template);
+    };
+    let known: HashSet<&String> = src
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|e| e.keys())
+        .collect();
+    let mut out = Map::new();
+    for (k, s) in template.as_object().into_iter().flatten() {
+        if let Some(v) = map.shift_remove(k) {
+            out.insert(k.clone(), downgrade_like(v, s, s));
+        }
+    }
+    out.extend(map.into_iter().filter(|(k, _)| known.contains(k)));
+    Value::Object(out)
+}
+
+fn insert_missing(json: &mut Value, key: &str, default: Value) {
+    if let Some(obj) = json.as_object_mut() {
+        obj.entry(key).or_insert(default);
+    }
+}
+
+fn remove(json: &mut Value, key: &str) -> Option<Value> {
+    json.as_object_mut().and_then(|obj| obj.shift_remove(key))
+}
+
+fn for_each_in(json: &mut Value, key: &str, f: impl FnMut(&mut Value)) {
+    if let Some(arr) = json.get_mut(key).and_then(Value::as_array_mut) {
+        arr.iter_mut().for_each(f);
+    }
+}
+
+// choreo 2025 beta files carried the version as a string ("v2025.0.0"), release files
+// as a number. 2024 files have no version and keep their samples at the top level
+fn choreo_version(json: &Value) -> std::result::Result<i64, String> {
+    match json.get("version") {
+        Some(Value::Number(n)) => n.as_i64().ok_or(format!("bad Choreo file version {}", n)),
+        Some(Value::String(_)) => Ok(0),
+        None if json.get("samples").is_some() => Err(
+            "Choreo 2024 trajectories aren't supported, open and save the project in \
+             Choreo 2025 or newer to upgrade it"
+                .to_owned(),
+        ),
+        _ => Err("not a Choreo trajectory (no version)".to_owned()),
+    }
+}
+
+impl Versioned for ChoreoData {
+    fn upgrade(json: &mut Value) -> std::result::Result<(), String> {
+        match choreo_version(json)? {
+            CHOREO_VERSION => {}
+            0 => {
+                insert_missing(json, "events", json!([]));
+                if let Some(traj) = json.get_mut("trajectory") {
+                    insert_missing(traj, "splits", json!([]));
+                    insert_missing(traj, "waypoints", json!([]));
+                }
+                if let Some(obj) = json.as_object_mut() {
+                    obj.insert("version".to_owned(), json!(CHOREO_VERSION));
+                }
+            }
+            v => {
+                return Err(format!(
+                    "Choreo file version {} is newer than the supported version {}",
+                    v, CHOREO_VERSION
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn is_current(json: &Value) -> bool {
+        choreo_version(json).is_ok_and(|v| v == CHOREO_VERSION)
+    }
+}
+
+// pathplanner 2024 wrote the version as the number 1.0, 2025 as the string "2025.0"
+fn pathplanner_version(json: &Value) -> std::result::Result<String, String> {
+    match json.get("version") {
+        Some(Value::Number(n)) => Ok(format!("{:.1}", n.as_f64().unwrap_or_default())),
+        Some(Value::String(s)) => Ok(s.clone()),
+        _ => Err("not a PathPlanner file (no version)".to_owned()),
+    }
+}
+
+fn check_pathplanner_version(json: &Value) -> std::result::Result<bool, String> {
+    match pathplanner_version(json)?.as_str() {
+        PATHPLANNER_VERSION => Ok(true),
+        "1.0" => Ok(false),
+        v => Err(format!(
+            "unsupported PathPlanner file version {} (supported: 1.0, {})",
+            v, PATHPLANNER_VERSION
+        )),
+    }
+}
+
+fn upgrade_constraints(constraints: &mut Value) {
+    insert_missing(constraints, "nominalVoltage", json!(12.0));
+    insert_missing(constraints, "unlimited", json!(false));
+}
+
+impl Versioned for PathData {
+    fn upgrade(json: &mut Value) -> std::result::Result<(), String> {
+        if check_pathplanner_version(json)? {
+            return Ok(());
+        }
+        // rotation targets keep rotateFast on the model, see drop_legacy
+        for_each_in(json, "constraintZones", |z| {
+            if let Some(c) = z.get_mut("constraints") {
+                upgrade_constraints(c);
+            }
+        });
+        for_each_in(json, "eventMarkers", |m| {
+            insert_missing(m, "endWaypointRelativePos", Value::Null)
+        });
+        if let Some(c) = json.get_mut("globalConstraints") {
+            upgrade_constraints(c);
+        }
+        if let Some(goal) = json.get_mut("goalEndState") {
+            remove(goal, "rotateFast");
+        }
+        let start = match remove(json, "previewStartingState") {
+            Some(Value::Object(s)) => Value::Object(s),
+            _ => json!({"velocity": 0.0, "rotation": 0.0}),
+        };
+        insert_missing(json, "idealStartingState", start);
+        insert_missing(json, "pointTowardsZones", json!([]));
+        if let Some(obj) = json.as_object_mut() {
+            obj.insert("version".to_owned(), json!(PATHPLANNER_VERSION));
+        }
+        Ok(())
+    }
+
+    fn is_current(json: &Value) -> bool {
+        check_pathplanner_version(json).unwrap_or(false)
+    }
+
+    fn drop_legacy(json: &mut Value) {
+        for_each_in(json, "rotationTargets", |t| {
+            remove(t, "rotateFast");
+        });
+    }
+
+    fn downgrade(json: Value, source: &Value, model: &Value) -> Value {
+        let start = json.get("idealStartingState").cloned();
+        let mut out = downgrade_like(json, source, model);
+        if let Some(obj) = out.as_object_mut() {
+            if let Some(version) = source.get("version") {
+                obj.insert("version".to_owned(), version.clone());
+            }
+            // the preview start was optional in 2024, keep it absent if it was
+            if let (Some(Value::Object(_)), Some(start)) =
+                (source.get("previewStartingState"), start)
+            {
+                let mut preview = Map::new();
+                preview.insert("rotation".to_owned(), start["rotation"].clone());
+                preview.insert("velocity".to_owned(), start["velocity"].clone());
+                obj.insert("previewStartingState".to_owned(), Value::Object(preview));
+            }
+        }
+        out
+    }
+}
+
+impl Versioned for AutoData {
+    fn upgrade(json: &mut Value) -> std::result::Result<(), String> {
+        if check_pathplanner_version(json)? {
+            return Ok(());
+        }
+        // 2024 autos reset odometry to an explicit starting pose. It stays on the model
+        // so it can be flipped and written back
+        let reset = json.get("startingPose").is_some_and(|p| !p.is_null());
+        insert_missing(json, "resetOdom", json!(reset));
+        insert_missing(json, "folder", Value::Null);
+        insert_missing(json, "choreoAuto", json!(false));
+        if let Some(obj) = json.as_object_mut() {
+            obj.insert("version".to_owned(), json!(PATHPLANNER_VERSION));
+        }
+        Ok(())
+    }
+
+    fn is_current(json: &Value) -> bool {
+        check_pathplanner_version(json).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::flip::{Field, Flippable, Transform2d};
+
+    fn read<T: Versioned>(json: &Value) -> Loaded<T> {
+        from_reader(json.to_string().as_bytes(), Path::new("test")).unwrap()
+    }
+
+    fn path_2024() -> Value {
+        let constraints = json!({
+            "maxVelocity": 3.0,
+            "maxAcceleration": 3.0,
+            "maxAngularVelocity": 540.0,
+            "maxAngularAcceleration": 720.0
+        });
+        json!({
+            "version": 1.0,
+            "waypoints": [
+                {
+                    "anchor": { "x": 2.0, "y": 6.0 },
+                    "prevControl": null,
+                    "nextControl": { "x": 3.0, "y": 6.0 },
+                    "isLocked": false,
+                    "linkedName": null
+                },
+                {
+                    "anchor": { "x": 6.0, "y": 3.0 },
+                    "prevControl": { "x": 6.0, "y": 4.0 },
+                    "nextControl": null,
+                    "isLocked": false,
+                    "linkedName": null
+                }
+            ],
+            "rotationTargets": [
+                { "waypointRelativePos": 0.25, "rotationDegrees": 45.0, "rotateFast": true },
+                { "waypointRelativePos": 0.5, "rotationDegrees": 90.0, "rotateFast": false }
+            ],
+            "constraintZones": [{
+                "name": "Slow",
+                "minWaypointRelativePos": 0.1,
+                "maxWaypointRelativePos": 0.3,
+                "constraints": constraints
+            }],
+            "eventMarkers": [
+                { "name": "Intake", "waypointRelativePos": 0.2, "command": null, "color": "red" },
+                { "name": "Shoot", "waypointRelativePos": 0.9, "command": null }
+            ],
+            "globalConstraints": constraints,
+            "goalEndState": { "velocity": 0.0, "rotation": 180.0, "rotateFast": true },
+            "reversed": false,
+            "folder": null,
+            "previewStartingState": { "rotation": 0.0, "velocity": 0.0 },
+            "useDefaultConstraints": true
+        })
+    }
+
+    #[test]
+    fn pathplanner_2024_round_trips() {
+        let source = path_2024();
+        let loaded = read::<PathData>(&source);
+        assert_eq!(loaded.to_value(false), source);
+
+        let latest = loaded.to_value(true);
+        assert_eq!(latest["version"], PATHPLANNER_VERSION);
+        assert!(latest["rotationTargets"][0].get("rotateFast").is_none());
+        assert!(latest.get("idealStartingState").is_some());
+    }
+
+    #[test]
+    fn pathplanner_2024_fields_move_with_their_element() {
+        let mut loaded = read::<PathData>(&path_2024());
+        loaded.data.reverse();
+        let out = loaded.to_value(false);
+        assert_eq!(out["version"], 1.0);
+        assert_eq!(
+            out["rotationTargets"],
+            json!([
+                { "waypointRelativePos": 0.5, "rotationDegrees": 90.0, "rotateFast": false },
+                { "waypointRelativePos": 0.75, "rotationDegrees": 45.0, "rotateFast": true }
+            ])
+        );
+        assert!(out["eventMarkers"][0].get("color").is_none());
+        assert_eq!(out["eventMarkers"][1]["color"], "red");
+        assert!(out["eventMarkers"][1]
+            .get("endWaypointRelativePos")
+            .is_none());
+        assert!(out.get("idealStartingState").is_none());
+        // the old goal rotation is where the path now starts
+        assert_eq!(out["previewStartingState"]["rotation"], 180.0);
+    }
+
+    fn choreo_v0() -> Value {
+        let waypoint = |x: f64, note: Option<&str>| {
+            let mut wp = json!({
+                "x": { "exp": format!("{} m", x), "val": x },
+                "y": { "exp": "4 m", "val": 4.0 },
+                "heading": { "exp": "0 rad", "val": 0.0 },
+                "intervals": 10,
+                "split": false,
+                "fixTranslation": true,
+                "fixHeading": true,
+                "overrideIntervals": false
+            });
+            if let Some(note) = note {
+                wp["note"] = json!(note);
+            }
+            wp
+        };
+        let sample = |t: f64, x: f64| {
+            json!({
+                "t": t, "x": x, "y": 4.0, "heading": 0.0, "vx": 2.0, "vy": 0.0,
+                "omega": 0.0, "ax": 0.0, "ay": 0.0, "alpha": 0.0,
+                "fx": [0.0, 0.0, 0.0, 0.0], "fy": [0.0, 0.0, 0.0, 0.0]
+            })
+        };
+        json!({
+            "name": "Test",
+            "version": "v2025.0.0",
+            "snapshot": {
+                "waypoints": [
+                    { "x": 2.0, "y": 4.0, "heading": 0.0, "intervals": 10, "split": false,
+                      "fixTranslation": true, "fixHeading": true, "overrideIntervals": false },
+                    { "x": 4.0, "y": 4.0, "heading": 0.0, "intervals": 10, "split": false,
+                      "fixTranslation": true, "fixHeading": true, "overrideIntervals": false }
+                ],
+                "constraints": [],
+                "targetDt": 0.05
+            },
+            "params": {
+                "waypoints": [waypoint(2.0, Some("start")), waypoint(4.0, None)],
+                "constraints": [],
+                "targetDt": { "exp": "0.05 s", "val": 0.05 }
+            },
+            "trajectory": {
+                "sampleType": "Swerve",
+                "samples": [sample(0.0, 2.0), sample(1.0, 4.0)]
+            }
+        })
+    }
+
+    #[test]
+    fn choreo_v0_round_trips() {
+        let source = choreo_v0();
+        let loaded = read::<ChoreoData>(&source);
+        assert!(loaded.data.events.is_empty());
+        assert_eq!(loaded.to_value(false), source);
+        assert_eq!(loaded.to_value(true)["version"], CHOREO_VERSION);
+    }
+
+    #[test]
+    fn choreo_v0_fields_move_with_their_element() {
+        let mut loaded = read::<ChoreoData>(&choreo_v0());
+        loaded
+            .data
+            .transform(&Transform2d::alliance(&Field::NOMINAL));
+        loaded.data.reverse();
+        let out = loaded.to_value(false);
+        assert_eq!(out["version"], "v2025.0.0");
+        assert!(out.get("events").is_none());
+        assert!(out["trajectory"].get("splits").is_none());
+        let waypoints = &out["params"]["waypoints"];
+        assert!(waypoints[0].get("note").is_none());
+        assert_eq!(waypoints[1]["note"], "start");
+        assert_eq!(waypoints[1]["x"]["val"], 16.54 - 2.0);
+    }
+}
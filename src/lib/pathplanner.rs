@@ -1,4 +1,5 @@
 pub mod commands {
+    use std::collections::HashMap;
 
     #[derive(serde::Serialize, serde::Deserialize, Clone)]
     #[serde(tag = "type", content = "data")]
@@ -16,6 +17,8 @@ pub mod commands {
         ParallelRaceGroup { commands: Vec<PPCommand> },
         #[serde(rename = "parallel")]
         ParallelCommandGroup { commands: Vec<PPCommand> },
+        #[serde(rename = "deadline")]
+        DeadlineCommandGroup { commands: Vec<PPCommand> },
         #[serde(rename = "wait")]
         WaitCommand {
             #[serde(rename = "waitTime")]
@@ -27,9 +30,10 @@ pub mod commands {
         pub fn get_command_list(&self) -> Option<&Vec<PPCommand>> {
             match self {
                 Self::NamedCommand { name: _ } => Option::None,
-                Self::SequentialCommand { commands: c } => Some(&c),
-                Self::ParallelCommandGroup { commands: c } => Some(&c),
-                Self::ParallelRaceGroup { commands: c } => Some(&c),
+                Self::SequentialCommand { commands: c } => Some(c),
+                Self::ParallelCommandGroup { commands: c } => Some(c),
+                Self::ParallelRaceGroup { commands: c } => Some(c),
+                Self::DeadlineCommandGroup { commands: c } => Some(c),
                 Self::PathFollowCommand { path_name: _ } => Option::None,
                 Self::WaitCommand { wait_time: _ } => Option::None,
             }
@@ -46,55 +50,88 @@ pub mod commands {
             }
         }
 
-        pub fn replace_path_commands(&self, names: &Vec<String>) -> Self {
-            let mut idx = 0;
-            self.replace_path_commands_inner(names, &mut idx)
+        pub fn named_commands(&self) -> Vec<String> {
+            let mut names = Vec::new();
+            self.parse_recursive(&mut |c| {
+                if let Self::NamedCommand { name: Some(name) } = c {
+                    names.push(name.clone());
+                }
+            });
+            names
         }
 
-        fn replace_path_commands_inner(&self, names: &Vec<String>, idx: &mut usize) -> Self {
-            match self {
-                Self::PathFollowCommand { path_name: _ } => {
-                    let new_name = names.get(*idx).cloned();
-                    *idx += 1;
-                    Self::PathFollowCommand {
-                        path_name: new_name,
-                    }
+        // every path followed, as often as it's followed
+        pub fn path_names(&self) -> Vec<String> {
+            let mut names = Vec::new();
+            self.parse_recursive(&mut |c| {
+                if let Self::PathFollowCommand {
+                    path_name: Some(name),
+                } = c
+                {
+                    names.push(name.clone());
                 }
+            });
+            names
+        }
+
+        // the same command, following `renamed[name]` wherever it followed `name`. Errs with
+        // the name of a path that isn't in `renamed`
+        pub fn replace_path_commands(
+            &self,
+            renamed: &HashMap<String, String>,
+        ) -> Result<Self, String> {
+            let replace_all = |commands: &Vec<PPCommand>| {
+                commands
+                    .iter()
+                    .map(|c| c.replace_path_commands(renamed))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            Ok(match self {
+                Self::PathFollowCommand { path_name } => Self::PathFollowCommand {
+                    path_name: match path_name {
+                        Some(name) => Some(renamed.get(name).cloned().ok_or(name.clone())?),
+                        None => None,
+                    },
+                },
                 Self::SequentialCommand { commands } => Self::SequentialCommand {
-                    commands: commands
-                        .iter()
-                        .map(|c| c.replace_path_commands_inner(names, idx))
-                        .collect(),
+                    commands: replace_all(commands)?,
                 },
-
                 Self::ParallelCommandGroup { commands } => Self::ParallelCommandGroup {
-                    commands: commands
-                        .iter()
-                        .map(|c| c.replace_path_commands_inner(names, idx))
-                        .collect(),
+                    commands: replace_all(commands)?,
                 },
-
                 Self::ParallelRaceGroup { commands } => Self::ParallelRaceGroup {
-                    commands: commands
-                        .iter()
-                        .map(|c| c.replace_path_commands_inner(names, idx))
-                        .collect(),
+                    commands: replace_all(commands)?,
+                },
+                Self::DeadlineCommandGroup { commands } => Self::DeadlineCommandGroup {
+                    commands: replace_all(commands)?,
                 },
-
                 Self::NamedCommand { name } => Self::NamedCommand { name: name.clone() },
                 Self::WaitCommand { wait_time } => Self::WaitCommand {
                     wait_time: *wait_time,
                 },
-            }
+            })
         }
     }
 }
 
 pub mod auto {
+    use std::collections::HashMap;
+
+    use crate::lib::{
+        flip::{Flippable, Transform2d},
+        pathplanner::path::PathPoint,
+    };
 
     #[derive(serde::Serialize, serde::Deserialize)]
     pub struct AutoData {
         pub version: String,
+        // only in 2024 autos, see migrate
+        #[serde(
+            rename = "startingPose",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub starting_pose: Option<AutoStartingPose>,
         pub command: crate::pathplanner::commands::PPCommand,
         #[serde(rename = "resetOdom")]
         pub reset_odom: bool,
@@ -103,25 +140,32 @@ pub mod auto {
         pub choreo_auto: bool,
     }
 
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub struct AutoStartingPose {
+        pub position: PathPoint,
+        pub rotation: f64,
+    }
+
+    impl Flippable for AutoStartingPose {
+        fn transform(&mut self, t: &Transform2d) {
+            self.position.transform(t);
+            self.rotation = t.heading_degrees(self.rotation);
+        }
+    }
+
     impl AutoData {
+        // the files the auto follows, each once and sorted by name
         pub fn get_filenames(&self) -> (Vec<String>, bool) {
             use crate::lib::pathplanner::commands::PPCommand;
             let mut vec: Vec<String> = Vec::new();
             let mut callback = |c: &PPCommand| {
                 if let PPCommand::PathFollowCommand { path_name: name } = c {
                     let filename = name.clone().unwrap_or(String::new());
-                    vec.push(if let Some(pos) = filename.rfind('.') {
-                        if self.choreo_auto {
-                            format!("{}.traj", &filename[..pos])
-                        } else {
-                            format!("{}.path", &filename[..pos])
-                        }
+                    let (stem, _) = split_name(&filename);
+                    vec.push(if self.choreo_auto {
+                        format!("{}.traj", stem)
                     } else {
-                        if self.choreo_auto {
-                            format!("{}.traj", &filename)
-                        } else {
-                            format!("{}.path", &filename)
-                        }
+                        format!("{}.path", stem)
                     });
                 }
             };
@@ -133,29 +177,63 @@ pub mod auto {
 
             (vec, self.choreo_auto)
         }
+
+        // follows the files in `renamed`, by file stem, under their new stems. Errs with the
+        // name of a path whose file isn't in `renamed`
+        pub fn rename_paths(&mut self, renamed: &HashMap<String, String>) -> Result<(), String> {
+            self.command = self
+                .command
+                .replace_path_commands(&renamed_paths(&self.command, renamed))?;
+            Ok(())
+        }
+    }
+
+    // the new name of each path `command` follows whose file, by stem, is in `renamed`
+    pub fn renamed_paths(
+        command: &crate::pathplanner::commands::PPCommand,
+        renamed: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        command
+            .path_names()
+            .into_iter()
+            .filter_map(|name| {
+                let (stem, split) = split_name(&name);
+                let new_name = format!("{}{}", renamed.get(stem)?, split);
+                Some((name, new_name))
+            })
+            .collect()
+    }
+
+    // a path command's name as the stem of the file it follows and, for one segment of a
+    // split choreo trajectory, the ".1" naming it
+    fn split_name(name: &str) -> (&str, &str) {
+        name.split_at(name.rfind('.').unwrap_or(name.len()))
     }
 }
 
 pub mod path {
-    use crate::lib::flip::{flip_xaxis, flip_yaxis, Flippable};
+    use crate::lib::{
+        flip::{Flippable, Transform2d},
+        util::{
+            self,
+            beizer::{self, Anchor, ArcLength},
+            Vec2d,
+        },
+    };
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathPoint {
         pub x: f64,
         pub y: f64,
     }
 
     impl Flippable for PathPoint {
-        fn flip_alliance(&mut self) {
-            self.x = flip_yaxis(self.x, self.y)[0];
-        }
-
-        fn flip_same_alliance(&mut self) {
-            self.y = flip_xaxis(self.x, self.y)[1];
+        fn transform(&mut self, t: &Transform2d) {
+            [self.x, self.y] = t.point([self.x, self.y]);
         }
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathWaypoint {
         pub anchor: PathPoint,
         #[serde(rename = "prevControl")]
@@ -166,31 +244,20 @@ pub mod path {
         pub is_locked: bool,
         #[serde(rename = "linkedName")]
         pub linked_name: Option<String>,
+        // fields only other pathplanner versions have, like 2024's rotateFast, kept on the
+        // element so they stay with it when a path is reversed
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl Flippable for PathWaypoint {
-        fn flip_alliance(&mut self) {
-            self.anchor.flip_alliance();
-            if self.prev_control.is_some() {
-                self.prev_control.as_mut().unwrap().flip_alliance();
-            }
-            if self.next_control.is_some() {
-                self.next_control.as_mut().unwrap().flip_alliance();
-            }
-
-            if self.linked_name.is_some() {
-                self.linked_name =
-                    Some(self.linked_name.as_mut().unwrap().to_owned() + " -- flipped");
+        fn transform(&mut self, t: &Transform2d) {
+            self.anchor.transform(t);
+            if let Some(prev_control) = self.prev_control.as_mut() {
+                prev_control.transform(t);
             }
-        }
-
-        fn flip_same_alliance(&mut self) {
-            self.anchor.flip_same_alliance();
-            if self.prev_control.is_some() {
-                self.prev_control.as_mut().unwrap().flip_same_alliance();
-            }
-            if self.next_control.is_some() {
-                self.next_control.as_mut().unwrap().flip_same_alliance();
+            if let Some(next_control) = self.next_control.as_mut() {
+                next_control.transform(t);
             }
 
             if self.linked_name.is_some() {
@@ -206,21 +273,17 @@ pub mod path {
         pub waypoint_relative_pos: f64,
         #[serde(rename = "rotationDegrees")]
         pub rotation_degrees: f64,
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl Flippable for PathRotationTarget {
-        fn flip_alliance(&mut self) {}
-
-        fn flip_same_alliance(&mut self) {
-            self.rotation_degrees = if self.rotation_degrees == 0.0 {
-                0.0
-            } else {
-                -self.rotation_degrees
-            };
+        fn transform(&mut self, t: &Transform2d) {
+            self.rotation_degrees = t.heading_degrees(self.rotation_degrees);
         }
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathConstraintZone {
         pub name: String,
         #[serde(rename = "minWaypointRelativePos")]
@@ -228,45 +291,61 @@ pub mod path {
         #[serde(rename = "maxWaypointRelativePos")]
         pub max_waypoint_relative_pos: f64,
         pub constraints: PathConstraints,
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathPointTowardsZone {
         #[serde(rename = "fieldPosition")]
-        field_position: PathPoint,
+        pub field_position: PathPoint,
         #[serde(rename = "rotationOffset")]
-        rotation_offset: f64,
+        pub rotation_offset: f64,
         #[serde(rename = "minWaypointRelativePos")]
         pub min_waypoint_relative_pos: f64,
         #[serde(rename = "maxWaypointRelativePos")]
         pub max_waypoint_relative_pos: f64,
-        name: String,
+        pub name: String,
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
-    impl Flippable for PathPointTowardsZone {
-        fn flip_alliance(&mut self) {}
+    impl PathPointTowardsZone {
+        pub fn contains(&self, pos: f64) -> bool {
+            pos >= self.min_waypoint_relative_pos && pos <= self.max_waypoint_relative_pos
+        }
 
-        fn flip_same_alliance(&mut self) {
-            self.field_position.flip_same_alliance();
-            self.rotation_offset = if self.rotation_offset == 0.0 {
-                0.0
-            } else {
-                -self.rotation_offset
-            };
+        // rotation in degrees a robot at `position` takes while inside the zone
+        pub fn rotation_at(&self, position: Vec2d) -> f64 {
+            Vec2d::from_pathpoint(&self.field_position)
+                .sub(position)
+                .angle()
+                .to_degrees()
+                + self.rotation_offset
         }
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    impl Flippable for PathPointTowardsZone {
+        fn transform(&mut self, t: &Transform2d) {
+            self.field_position.transform(t);
+            // relative to the direction of the field position, which turns with it
+            self.rotation_offset = t.rate(self.rotation_offset);
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathEventMarker {
-        name: String,
+        pub name: String,
         #[serde(rename = "waypointRelativePos")]
-        waypoint_relative_pos: f64,
+        pub waypoint_relative_pos: f64,
         #[serde(rename = "endWaypointRelativePos")]
-        end_waypoint_relative_pos: Option<f64>,
-        command: Option<crate::pathplanner::commands::PPCommand>,
+        pub end_waypoint_relative_pos: Option<f64>,
+        pub command: Option<crate::pathplanner::commands::PPCommand>,
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathConstraints {
         #[serde(rename = "maxVelocity")]
         pub max_velocity: f64,
@@ -288,18 +367,12 @@ pub mod path {
     }
 
     impl Flippable for PathGoalState {
-        fn flip_alliance(&mut self) {}
-
-        fn flip_same_alliance(&mut self) {
-            self.rotation = if self.rotation == 0.0 {
-                0.0
-            } else {
-                -self.rotation
-            };
+        fn transform(&mut self, t: &Transform2d) {
+            self.rotation = t.heading_degrees(self.rotation);
         }
     }
 
-    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
     pub struct PathData {
         pub version: String,
         pub waypoints: Vec<PathWaypoint>,
@@ -324,30 +397,336 @@ pub mod path {
     }
 
     impl Flippable for PathData {
-        fn flip_alliance(&mut self) {
-            self.waypoints.iter_mut().for_each(Flippable::flip_alliance);
+        fn transform(&mut self, t: &Transform2d) {
+            self.waypoints.iter_mut().for_each(|w| w.transform(t));
             self.rotation_targets
                 .iter_mut()
-                .for_each(Flippable::flip_alliance);
+                .for_each(|r| r.transform(t));
             self.point_towards_zones
                 .iter_mut()
-                .for_each(Flippable::flip_alliance);
-            self.goal_end_state.flip_alliance();
-            self.ideal_starting_state.flip_alliance();
+                .for_each(|z| z.transform(t));
+            self.goal_end_state.transform(t);
+            self.ideal_starting_state.transform(t);
         }
+    }
 
-        fn flip_same_alliance(&mut self) {
+    impl PathData {
+        pub fn anchors(&self) -> Vec<Anchor> {
             self.waypoints
-                .iter_mut()
-                .for_each(Flippable::flip_same_alliance);
-            self.rotation_targets
-                .iter_mut()
-                .for_each(Flippable::flip_same_alliance);
-            self.point_towards_zones
-                .iter_mut()
-                .for_each(Flippable::flip_same_alliance);
-            self.goal_end_state.flip_same_alliance();
-            self.ideal_starting_state.flip_same_alliance();
+                .iter()
+                .map(|pw| Anchor {
+                    position: Vec2d::from_pathpoint(&pw.anchor),
+                    control_in: Vec2d::option_from_pathpoint(&pw.prev_control),
+                    control_out: Vec2d::option_from_pathpoint(&pw.next_control),
+                })
+                .collect()
+        }
+
+        // holonomic rotation in degrees at a waypoint relative position. Like pathplanner,
+        // rotation is cosine interpolated between the surrounding rotation targets by
+        // distance along the path, and point towards zones act as targets over their range
+        pub fn rotation_at(&self, arc: &ArcLength, pos: f64) -> f64 {
+            let anchors = arc.anchors();
+            if let Some(zone) = self.point_towards_zones.iter().find(|z| z.contains(pos)) {
+                return zone.rotation_at(beizer::point_at(anchors, pos));
+            }
+            let mut prev = (0.0, self.ideal_starting_state.rotation);
+            let mut next = (
+                (self.waypoints.len() - 1) as f64,
+                self.goal_end_state.rotation,
+            );
+            let zone_edges = self.point_towards_zones.iter().flat_map(|z| {
+                [z.min_waypoint_relative_pos, z.max_waypoint_relative_pos]
+                    .map(|p| (p, z.rotation_at(beizer::point_at(anchors, p))))
+            });
+            for (target_pos, rotation) in self
+                .rotation_targets
+                .iter()
+                .map(|r| (r.waypoint_relative_pos, r.rotation_degrees))
+                .chain(zone_edges)
+            {
+                if target_pos <= pos && target_pos >= prev.0 {
+                    prev = (target_pos, rotation);
+                }
+                if target_pos > pos && target_pos < next.0 {
+                    next = (target_pos, rotation);
+                }
+            }
+            let s0 = arc.distance_at(prev.0);
+            let s1 = arc.distance_at(next.0);
+            let t = if s1 - s0 > 1e-9 {
+                (arc.distance_at(pos) - s0) / (s1 - s0)
+            } else {
+                1.0
+            };
+            util::cosine_interpolate(prev.1.to_radians(), next.1.to_radians(), t).to_degrees()
         }
+
+        // (position, rotation in radians) every `spacing` meters along the path
+        pub fn rotation_profile(&self, arc: &ArcLength, spacing: f64) -> Vec<(Vec2d, f64)> {
+            arc.sample_evenly(spacing)
+                .into_iter()
+                .map(|(s, p)| (p, self.rotation_at(arc, arc.t_at_distance(s)).to_radians()))
+                .collect()
+        }
+
+        // the same path run from its end to its start. Everything placed by waypoint
+        // relative position is moved to where it was measured from the other end, so the
+        // robot holds the same rotation and runs the same commands at each point
+        pub fn reverse(&mut self) {
+            let last = self.waypoints.len().saturating_sub(1) as f64;
+            let flip_range = |min: &mut f64, max: &mut f64| {
+                (*min, *max) = (last - *max, last - *min);
+            };
+            self.waypoints.reverse();
+            for wp in &mut self.waypoints {
+                std::mem::swap(&mut wp.prev_control, &mut wp.next_control);
+            }
+            self.rotation_targets.reverse();
+            for target in &mut self.rotation_targets {
+                target.waypoint_relative_pos = last - target.waypoint_relative_pos;
+            }
+            for zone in &mut self.constraint_zones {
+                flip_range(
+                    &mut zone.min_waypoint_relative_pos,
+                    &mut zone.max_waypoint_relative_pos,
+                );
+            }
+            for zone in &mut self.point_towards_zones {
+                flip_range(
+                    &mut zone.min_waypoint_relative_pos,
+                    &mut zone.max_waypoint_relative_pos,
+                );
+            }
+            self.event_markers.reverse();
+            for marker in &mut self.event_markers {
+                match marker.end_waypoint_relative_pos.as_mut() {
+                    Some(end) => flip_range(&mut marker.waypoint_relative_pos, end),
+                    None => marker.waypoint_relative_pos = last - marker.waypoint_relative_pos,
+                }
+            }
+            std::mem::swap(&mut self.ideal_starting_state, &mut self.goal_end_state);
+            // a differential drive keeps facing the way it did at each point by driving
+            // the other way
+            self.reversed = !self.reversed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, f64::consts::PI};
+
+    use super::{auto::AutoData, path::PathData};
+    use crate::lib::{
+        flip::{Field, Flippable, Transform2d, FIELD_X},
+        util::beizer::{self, ArcLength},
+    };
+
+    fn auto(paths: &[&str], choreo: bool) -> AutoData {
+        let commands: Vec<serde_json::Value> = paths
+            .iter()
+            .map(|p| serde_json::json!({ "type": "path", "data": { "pathName": p } }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "version": "2025.0",
+            "command": { "type": "sequential", "data": { "commands": commands } },
+            "resetOdom": true,
+            "folder": null,
+            "choreoAuto": choreo
+        }))
+        .unwrap()
+    }
+
+    fn path() -> PathData {
+        let waypoint = |x: f64, prev: Option<f64>, next: Option<f64>| {
+            let control = |cx: Option<f64>| cx.map(|cx| serde_json::json!({ "x": cx, "y": 1.0 }));
+            serde_json::json!({
+                "anchor": { "x": x, "y": 1.0 },
+                "prevControl": control(prev),
+                "nextControl": control(next),
+                "isLocked": false,
+                "linkedName": null
+            })
+        };
+        let constraints = serde_json::json!({
+            "maxVelocity": 3.0,
+            "maxAcceleration": 3.0,
+            "maxAngularVelocity": 540.0,
+            "maxAngularAcceleration": 720.0,
+            "nominalVoltage": 12.0,
+            "unlimited": false
+        });
+        serde_json::from_value(serde_json::json!({
+            "version": "2025.0",
+            "waypoints": [
+                waypoint(1.0, None, Some(1.5)),
+                waypoint(2.0, Some(1.5), Some(2.5)),
+                waypoint(3.0, Some(2.5), None)
+            ],
+            "rotationTargets": [{ "waypointRelativePos": 0.5, "rotationDegrees": 90.0 }],
+            "constraintZones": [{
+                "name": "slow",
+                "minWaypointRelativePos": 0.25,
+                "maxWaypointRelativePos": 0.75,
+                "constraints": constraints
+            }],
+            "pointTowardsZones": [{
+                "fieldPosition": { "x": 4.0, "y": 3.0 },
+                "rotationOffset": 10.0,
+                "minWaypointRelativePos": 1.0,
+                "maxWaypointRelativePos": 1.5,
+                "name": "speaker"
+            }],
+            "eventMarkers": [
+                { "name": "intake", "waypointRelativePos": 0.5, "endWaypointRelativePos": 1.25, "command": null },
+                { "name": "shoot", "waypointRelativePos": 1.75, "endWaypointRelativePos": null, "command": null }
+            ],
+            "globalConstraints": constraints,
+            "goalEndState": { "velocity": 0.0, "rotation": 45.0 },
+            "reversed": false,
+            "folder": null,
+            "idealStartingState": { "velocity": 0.0, "rotation": 0.0 },
+            "useDefaultConstraints": true
+        }))
+        .unwrap()
+    }
+
+    fn renamed(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn paths_are_renamed_by_name() {
+        // out of alphabetical order, with one path followed twice
+        let mut data = auto(&["B", "A", "B"], false);
+        assert_eq!(
+            data.get_filenames().0,
+            vec!["A.path".to_owned(), "B.path".to_owned()]
+        );
+        data.rename_paths(&renamed(&[("A", "A2"), ("B", "B2")]))
+            .unwrap();
+        assert_eq!(data.command.path_names(), vec!["B2", "A2", "B2"]);
+    }
+
+    #[test]
+    fn split_trajectories_keep_their_segment() {
+        let mut data = auto(&["T.1", "T.0"], true);
+        data.rename_paths(&renamed(&[("T", "T2")])).unwrap();
+        assert_eq!(data.command.path_names(), vec!["T2.1", "T2.0"]);
+    }
+
+    #[test]
+    fn rotation_eases_between_targets_by_distance() {
+        let data = path();
+        let arc = ArcLength::new(&data.anchors());
+        assert!(data.rotation_at(&arc, 0.0).abs() < 1e-9);
+        assert!((data.rotation_at(&arc, 0.5) - 90.0).abs() < 1e-9);
+        assert!((data.rotation_at(&arc, 2.0) - 45.0).abs() < 1e-9);
+        // halfway along, not halfway in t, the cosine easing is halfway too
+        let halfway = arc.t_at_distance(arc.distance_at(0.5) / 2.0);
+        assert!((data.rotation_at(&arc, halfway) - 45.0).abs() < 0.01);
+        // inside the zone the robot faces its position
+        let position = beizer::point_at(arc.anchors(), 1.25);
+        let facing = (3.0 - position.y).atan2(4.0 - position.x).to_degrees() + 10.0;
+        assert!((data.rotation_at(&arc, 1.25) - facing).abs() < 1e-9);
+    }
+
+    #[test]
+    fn alliance_flip_moves_point_towards_zones() {
+        let mut data = path();
+        data.transform(&Transform2d::alliance(&Field::NOMINAL));
+        let zone = &data.point_towards_zones[0];
+        assert!((zone.field_position.x - (FIELD_X - 4.0)).abs() < 1e-9);
+        assert_eq!(zone.field_position.y, 3.0);
+        // like the rotation targets, left as it was
+        assert_eq!(zone.rotation_offset, 10.0);
+        assert_eq!(data.rotation_targets[0].rotation_degrees, 90.0);
+    }
+
+    #[test]
+    fn mirrored_alliance_flip_keeps_facing_point_towards_zones() {
+        let mut data = path();
+        data.transform(&Transform2d::mirrored_alliance(&Field::NOMINAL));
+        let zone = &data.point_towards_zones[0];
+        assert_eq!(zone.rotation_offset, -10.0);
+        // still facing the moved position from the moved waypoint
+        let waypoint = &data.waypoints[1].anchor;
+        let bearing =
+            (zone.field_position.y - waypoint.y).atan2(zone.field_position.x - waypoint.x);
+        let original = path();
+        let (from, to) = (
+            &original.waypoints[1].anchor,
+            &original.point_towards_zones[0].field_position,
+        );
+        let mirrored = PI - (to.y - from.y).atan2(to.x - from.x);
+        assert!((bearing - mirrored).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reversed_paths_run_from_the_goal() {
+        let mut data = path();
+        data.reverse();
+        let anchors: Vec<f64> = data.waypoints.iter().map(|w| w.anchor.x).collect();
+        assert_eq!(anchors, vec![3.0, 2.0, 1.0]);
+        let controls: Vec<_> = data
+            .waypoints
+            .iter()
+            .map(|w| {
+                (
+                    w.prev_control.as_ref().map(|c| c.x),
+                    w.next_control.as_ref().map(|c| c.x),
+                )
+            })
+            .collect();
+        assert_eq!(
+            controls,
+            vec![(None, Some(2.5)), (Some(2.5), Some(1.5)), (Some(1.5), None)]
+        );
+        assert_eq!(data.rotation_targets[0].waypoint_relative_pos, 1.5);
+        let zone = &data.constraint_zones[0];
+        assert_eq!(
+            (
+                zone.min_waypoint_relative_pos,
+                zone.max_waypoint_relative_pos
+            ),
+            (1.25, 1.75)
+        );
+        let zone = &data.point_towards_zones[0];
+        assert_eq!(
+            (
+                zone.min_waypoint_relative_pos,
+                zone.max_waypoint_relative_pos
+            ),
+            (0.5, 1.0)
+        );
+        let markers: Vec<_> = data
+            .event_markers
+            .iter()
+            .map(|m| {
+                (
+                    m.name.as_str(),
+                    m.waypoint_relative_pos,
+                    m.end_waypoint_relative_pos,
+                )
+            })
+            .collect();
+        assert_eq!(
+            markers,
+            vec![("shoot", 0.25, None), ("intake", 0.75, Some(1.5))]
+        );
+        assert_eq!(data.ideal_starting_state.rotation, 45.0);
+        assert_eq!(data.goal_end_state.rotation, 0.0);
+        assert!(data.reversed);
+    }
+
+    #[test]
+    fn path_without_counterpart_is_an_error() {
+        let mut data = auto(&["A", "B"], false);
+        let err = data.rename_paths(&renamed(&[("A", "A2")])).err();
+        assert_eq!(err.as_deref(), Some("B"));
     }
 }
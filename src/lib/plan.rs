@@ -0,0 +1,247 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::lib::error::{Result, WithPath};
+
+// every file a flip would write, worked out before anything touches the disk so it can be
+// shown and confirmed first
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Change {
+    Create,
+    // an existing file replaced by an output, which needs consent
+    Overwrite,
+    // an edit to a file the flip reads as well: settings.json folders, .chor variables
+    Modify,
+}
+
+pub struct PlannedWrite {
+    pub path: PathBuf,
+    pub change: Change,
+    pub contents: String,
+}
+
+#[derive(Default)]
+pub struct WritePlan {
+    pub writes: Vec<PlannedWrite>,
+}
+
+impl WritePlan {
+    // an output file
+    pub fn output(&mut self, path: &Path, contents: String) {
+        let change = if path.exists() {
+            Change::Overwrite
+        } else {
+            Change::Create
+        };
+        self.insert(path, change, contents);
+    }
+
+    // an edit to an existing file, left out when it changes nothing
+    pub fn modify(&mut self, path: &Path, contents: String) -> Result<()> {
+        if self.read(path)? != contents {
+            self.insert(path, Change::Modify, contents);
+        }
+        Ok(())
+    }
+
+    // a file as it will be once the plan is written, so edits build on each other
+    pub fn read(&self, path: &Path) -> Result<String> {
+        match self.writes.iter().find(|w| w.path == path) {
+            Some(w) => Ok(w.contents.clone()),
+            None => fs::read_to_string(path).with_path(path),
+        }
+    }
+
+    fn insert(&mut self, path: &Path, change: Change, contents: String) {
+        match self.writes.iter_mut().find(|w| w.path == path) {
+            // a file planned twice keeps where it first appeared and what it first was
+            Some(w) => w.contents = contents,
+            None => self.writes.push(PlannedWrite {
+                path: path.to_path_buf(),
+                change,
+                contents,
+            }),
+        }
+    }
+
+    pub fn overwrites(&self) -> usize {
+        self.writes
+            .iter()
+            .filter(|w| w.change == Change::Overwrite)
+            .count()
+    }
+
+    // writes everything or nothing: each file is staged next to its destination, and only
+    // once all of them are staged are they renamed into place. If a rename fails the files
+    // already moved are put back the way they were
+    pub fn commit(&self) -> Result<()> {
+        let originals: Vec<Option<Vec<u8>>> =
+            self.writes.iter().map(|w| fs::read(&w.path).ok()).collect();
+        let mut staged = Vec::new();
+        for w in &self.writes {
+            let tmp = staging_path(&w.path);
+            if let Err(err) = fs::write(&tmp, &w.contents).with_path(&tmp) {
+                staged.push(tmp);
+                remove_all(&staged);
+                return Err(err);
+            }
+            staged.push(tmp);
+        }
+        for (i, (w, tmp)) in self.writes.iter().zip(&staged).enumerate() {
+            if let Err(err) = fs::rename(tmp, &w.path).with_path(&w.path) {
+                for (done, original) in self.writes[..i].iter().zip(&originals) {
+                    // best effort, the error that started the rollback is the one to report
+                    let _ = match original {
+                        Some(bytes) => fs::write(&done.path, bytes),
+                        None => fs::remove_file(&done.path),
+                    };
+                }
+                remove_all(&staged[i..]);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+// a hidden file in the same folder, so the rename stays on one filesystem
+fn staging_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.pathflip-{}", name, std::process::id()))
+}
+
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Change::Create => "create",
+            Change::Overwrite => "overwrite",
+            Change::Modify => "modify",
+        })
+    }
+}
+
+impl fmt::Display for WritePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for w in &self.writes {
+            writeln!(f, "{:<9} {}", w.change, w.path.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty folder of its own for each test
+    fn folder(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pathflip-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn nothing_is_written_until_the_plan_is_committed() {
+        let dir = folder("plan");
+        let (existing, settings, new) = (
+            dir.join("a.path"),
+            dir.join("settings.json"),
+            dir.join("b.path"),
+        );
+        fs::write(&existing, "old").unwrap();
+        fs::write(&settings, "folders").unwrap();
+
+        let mut plan = WritePlan::default();
+        plan.output(&existing, "new".to_owned());
+        plan.output(&new, "new".to_owned());
+        // an edit that changes nothing isn't planned, and later edits see earlier ones
+        plan.modify(&settings, "folders".to_owned()).unwrap();
+        assert!(plan.writes.iter().all(|w| w.path != settings));
+        plan.modify(&settings, "folders, Flipped".to_owned())
+            .unwrap();
+        assert_eq!(plan.read(&settings).unwrap(), "folders, Flipped");
+        // planned again, the file keeps its place and how it changes
+        plan.output(&new, "newer".to_owned());
+
+        let changes: Vec<_> = plan
+            .writes
+            .iter()
+            .map(|w| (w.path.clone(), w.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (existing.clone(), Change::Overwrite),
+                (new.clone(), Change::Create),
+                (settings.clone(), Change::Modify),
+            ]
+        );
+        assert_eq!(plan.overwrites(), 1);
+        assert_eq!(files_in(&dir), vec!["a.path", "settings.json"]);
+        assert_eq!(fs::read_to_string(&settings).unwrap(), "folders");
+
+        plan.commit().unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&new).unwrap(), "newer");
+        assert_eq!(fs::read_to_string(&settings).unwrap(), "folders, Flipped");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_commit_restores_what_was_written() {
+        let dir = folder("rollback");
+        let (existing, new) = (dir.join("a.path"), dir.join("b.path"));
+        fs::write(&existing, "old").unwrap();
+        // a folder can't be renamed over, so the last write fails once everything is staged
+        let blocked = dir.join("c.path");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), "").unwrap();
+
+        let mut plan = WritePlan::default();
+        plan.output(&existing, "new".to_owned());
+        plan.output(&new, "new".to_owned());
+        plan.output(&blocked, "new".to_owned());
+        assert!(plan.commit().is_err());
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!new.exists());
+        // and no staged files are left behind
+        assert_eq!(files_in(&dir), vec!["a.path", "c.path"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_staging_writes_nothing() {
+        let dir = folder("staging");
+        let existing = dir.join("a.path");
+        fs::write(&existing, "old").unwrap();
+
+        let mut plan = WritePlan::default();
+        plan.output(&existing, "new".to_owned());
+        plan.output(&dir.join("missing").join("b.path"), "new".to_owned());
+        assert!(plan.commit().is_err());
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert_eq!(files_in(&dir), vec!["a.path"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
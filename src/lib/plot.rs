@@ -1,19 +1,21 @@
 use std::{
-    fs::{self, File},
-    io::{Result, Write},
+    collections::HashMap,
     path::{Path, PathBuf},
 };
 
 use eframe::egui::{self, Color32, ColorImage, TextureHandle};
-use egui_plot::{Line, PlotImage, PlotPoint, Points};
-use serde::Serialize;
-use serde_json::{ser::PrettyFormatter, Serializer, Value};
+use egui_plot::{Line, PlotImage, PlotPoint, Points, Text};
+use serde_json::Value;
 
 use crate::{
     lib::{
-        chor,
+        calibration, chor,
+        error::{Error, Result, WithPath},
         flip::{self, Flippable},
+        format, migrate,
         pathplanner::{self},
+        plan::WritePlan,
+        project, symmetry,
         util::{self, Vec2d},
     },
     FlipFileType,
@@ -21,17 +23,47 @@ use crate::{
 
 type LinePoints = Vec<Vec<[f64; 2]>>;
 
+const HOVER_RADIUS_M: f64 = 0.3;
+const SAMPLE_SPACING_M: f64 = 0.05;
+const HEADING_TICK_SPACING_M: f64 = 0.25;
+const GHOST_SPACING_M: f64 = 1.0;
+const CONTINUITY_TOL_M: f64 = 0.05;
+const CONTINUITY_TOL_RAD: f64 = 0.087;
+// deviations from a mirror shade from green to red up to this, and smaller ones along the
+// route aren't drawn
+const DEVIATION_FULL_M: f64 = 0.25;
+const DEVIATION_MIN_M: f64 = 0.01;
+const ZONE_COLORS: [Color32; 3] = [
+    Color32::from_rgb(255, 140, 0),
+    Color32::from_rgb(220, 60, 220),
+    Color32::from_rgb(120, 220, 60),
+];
+// one per variant previewed together, in the order they're given
+const VARIANT_COLORS: [Color32; 3] = [
+    Color32::from_rgb(0, 200, 255),
+    Color32::from_rgb(255, 90, 90),
+    Color32::from_rgb(255, 210, 0),
+];
+
 pub trait Plotter {
     fn reset(&mut self);
     fn share_bg(&mut self, img: Option<TextureHandle>);
-    fn gen(&mut self, filepath: &String, r_xm: f64, r_ym: f64, same_alliance: bool) -> Result<()>;
+    fn gen(
+        &mut self,
+        filepath: &Path,
+        r_xm: f64,
+        r_ym: f64,
+        transform: &flip::Transform2d,
+    ) -> Result<()>;
     fn plot(&mut self, col: &Color32, ctx: &egui::Context, ui: &mut egui::Ui) -> Result<()>;
-    fn send_flip(
+    // adds the files flipping `inputfile` writes to `plan`
+    fn plan_flip(
         &self,
         inputfile: String,
-        outputfile: String,
-        is_same_alliance: bool,
-        auto_file_names: Option<&Vec<String>>,
+        outputfile: &Path,
+        opts: &flip::FlipOptions,
+        auto_outputs: Option<&[PathBuf]>,
+        plan: &mut WritePlan,
     ) -> Result<()>;
 }
 
@@ -41,6 +73,11 @@ pub struct ChoreoPlotter {
     sample_mirr_segs: LinePoints,
     wp_squares: LinePoints,
     wp_mirr_squares: LinePoints,
+    events: Vec<Annotation>,
+    trajs: Vec<(chor::ChoreoTraj, chor::ChoreoTraj)>,
+    robot_size: [f64; 2],
+    scrub_t: f64,
+    warnings: Vec<String>,
     bg_tex: Option<TextureHandle>,
 }
 
@@ -52,6 +89,11 @@ impl Default for ChoreoPlotter {
             sample_mirr_segs: Vec::new(),
             wp_squares: Vec::new(),
             wp_mirr_squares: Vec::new(),
+            events: Vec::new(),
+            trajs: Vec::new(),
+            robot_size: [0.0, 0.0],
+            scrub_t: 0.0,
+            warnings: Vec::new(),
             bg_tex: Option::None,
         }
     }
@@ -64,17 +106,42 @@ impl Plotter for ChoreoPlotter {
         self.sample_mirr_segs.clear();
         self.wp_squares.clear();
         self.wp_mirr_squares.clear();
+        self.events.clear();
+        self.trajs.clear();
+        self.scrub_t = 0.0;
+        self.warnings.clear();
     }
 
     fn share_bg(&mut self, img: Option<TextureHandle>) {
         self.bg_tex = img;
     }
 
-    fn gen(&mut self, filepath: &String, r_ym: f64, r_xm: f64, same_alli: bool) -> Result<()> {
-        use std::fs::File;
-
-        let file = File::open(filepath)?;
-        let data: chor::ChoreoData = serde_json::from_reader(&file)?;
+    fn gen(
+        &mut self,
+        filepath: &Path,
+        r_ym: f64,
+        r_xm: f64,
+        transform: &flip::Transform2d,
+    ) -> Result<()> {
+        let data = migrate::load::<chor::ChoreoData>(filepath)?.data;
+        let name = filepath
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.warnings.extend(
+            data.stale_reasons()
+                .into_iter()
+                .map(|r| format!("{}: {}", name, r)),
+        );
+        match chor::ChoreoProject::find(filepath, &WritePlan::default()) {
+            Ok(Some(project)) => self.warnings.extend(
+                data.expression_errors(&project.variables().scope())
+                    .into_iter()
+                    .map(|r| format!("{}: {}", name, r)),
+            ),
+            Ok(None) => {}
+            Err(err) => self.warnings.push(err.to_string()),
+        }
         let samples = &data.trajectory.samples;
         let waypoints = &data.params.waypoints;
 
@@ -88,16 +155,7 @@ impl Plotter for ChoreoPlotter {
         self.velocities
             .extend(samples.iter().map(|s| (s.vx * s.vx + s.vy * s.vy).sqrt()));
 
-        let mirr_func = if same_alli {
-            flip::flip_xaxis
-        } else {
-            flip::flip_yaxis
-        };
-        let mirr_cs = if same_alli {
-            Flippable::flip_same_alliance
-        } else {
-            Flippable::flip_alliance
-        };
+        let mirr_cs = |s: &mut chor::ChoreoSample| s.transform(transform);
 
         for i in 0..samples.len() - 1 {
             let mut smp_window = [samples[i].clone(), samples[i + 1].clone()];
@@ -120,7 +178,11 @@ impl Plotter for ChoreoPlotter {
 
         let mirred_wps: Vec<(PlotPoint, f64)> = wps
             .iter()
-            .map(|wp| (PlotPoint::from(mirr_func(wp.0.x, wp.0.y)), -wp.1))
+            .map(|wp| {
+                let mut pose = util::Pose2d::new(wp.0.x, wp.0.y, wp.1);
+                pose.transform(transform);
+                (PlotPoint::new(pose.x, pose.y), pose.heading)
+            })
             .collect();
 
         for (_i, p) in wps.iter().enumerate() {
@@ -133,6 +195,57 @@ impl Plotter for ChoreoPlotter {
                 .push(draw_rotate_square_rect([p.0.x, p.0.y], r_ym, r_xm, p.1));
         }
 
+        for event in &data.events {
+            let Some(t) = data.event_timestamp(event) else {
+                continue;
+            };
+            let commands = event.named_commands();
+            let label = if commands.is_empty() {
+                format!("{} @ {:.2} s", event.name, t)
+            } else {
+                format!("{} [{}] @ {:.2} s", event.name, commands.join(", "), t)
+            };
+            let Some(sample) = data.trajectory.sample_at(t) else {
+                continue;
+            };
+            let mut sample_mirr = sample.clone();
+            mirr_cs(&mut sample_mirr);
+            for (s, mirrored) in [(sample, false), (sample_mirr, true)] {
+                let tip = [
+                    s.x + s.heading.cos() * r_xm / 2.0,
+                    s.y + s.heading.sin() * r_xm / 2.0,
+                ];
+                self.events.push(Annotation {
+                    color: Color32::YELLOW,
+                    span: vec![[s.x, s.y]],
+                    link: Some(vec![[s.x, s.y], tip]),
+                    label: label.clone(),
+                    mirrored,
+                });
+            }
+        }
+
+        // autos chain trajectories, flag any jump between one's end and the next's start
+        if let (Some((prev, _)), Some(start)) = (self.trajs.last(), data.trajectory.start_pose()) {
+            if let Some(end) = prev.end_pose() {
+                let gap = end.translation().dist(start.translation());
+                let turn = util::wrap_angle(start.heading - end.heading).abs();
+                if gap > CONTINUITY_TOL_M || turn > CONTINUITY_TOL_RAD {
+                    self.events.push(Annotation {
+                        color: Color32::RED,
+                        span: vec![[end.x, end.y], [start.x, start.y]],
+                        link: None,
+                        label: format!("gap {:.2} m, {:.0}\u{b0}", gap, turn.to_degrees()),
+                        mirrored: false,
+                    });
+                }
+            }
+        }
+        let mut traj_mirr = data.trajectory.clone();
+        traj_mirr.transform(transform);
+        self.trajs.push((data.trajectory, traj_mirr));
+        self.robot_size = [r_ym, r_xm];
+
         Ok(())
     }
 
@@ -150,6 +263,21 @@ impl Plotter for ChoreoPlotter {
             Color32::GRAY.b(),
             25_u8,
         );
+        let total_t: f64 = self.trajs.iter().map(|(t, _)| t.duration()).sum();
+        if total_t > 0.0 {
+            ui.add(egui::Slider::new(&mut self.scrub_t, 0.0..=total_t).text("t (s)"));
+        }
+        let mut scrub_poses = Vec::new();
+        let mut traj_start = 0.0;
+        for (traj, traj_mirr) in &self.trajs {
+            let local_t = self.scrub_t - traj_start;
+            if local_t <= traj.duration() {
+                scrub_poses.extend(traj.sample_at(local_t).map(|s| (s, false)));
+                scrub_poses.extend(traj_mirr.sample_at(local_t).map(|s| (s, true)));
+                break;
+            }
+            traj_start += traj.duration();
+        }
         Plot::new("Choreo Path")
             .view_aspect((flip::FIELD_X / flip::FIELD_Y) as f32)
             .data_aspect(1.0)
@@ -177,10 +305,10 @@ impl Plotter for ChoreoPlotter {
                     );
                     plot_ui.line(
                         Line::new("wp_square", pts.clone())
-                        .color(*col)
-                        .style(egui_plot::LineStyle::Solid)
-                        .fill((pts.iter().map(|p| p[1]).sum::<f64>() / pts.len() as f64) as f32)
-                        .width(4.0),
+                            .color(*col)
+                            .style(egui_plot::LineStyle::Solid)
+                            .fill((pts.iter().map(|p| p[1]).sum::<f64>() / pts.len() as f64) as f32)
+                            .width(4.0),
                     );
                 }
                 for pts in &self.wp_mirr_squares {
@@ -232,52 +360,134 @@ impl Plotter for ChoreoPlotter {
                             .width(4.0),
                     );
                 }
+                draw_annotations(plot_ui, &self.events, gray_blend2);
+                for (sample, mirrored) in &scrub_poses {
+                    plot_ui.line(
+                        Line::new(
+                            "scrub_pose",
+                            draw_rotate_square_rect(
+                                [sample.x, sample.y],
+                                self.robot_size[0],
+                                self.robot_size[1],
+                                sample.heading,
+                            ),
+                        )
+                        .color(if *mirrored {
+                            Color32::WHITE.blend(gray_blend2)
+                        } else {
+                            Color32::WHITE
+                        })
+                        .width(3.0),
+                    );
+                }
             });
         Ok(())
     }
 
-    fn send_flip(
+    fn plan_flip(
         &self,
         inputfile: String,
-        outputfile: String,
-        is_same_alliance: bool,
-        _: Option<&Vec<String>>,
+        outputfile: &Path,
+        opts: &flip::FlipOptions,
+        _: Option<&[PathBuf]>,
+        plan: &mut WritePlan,
     ) -> Result<()> {
-        let mut file = File::open(inputfile)?;
-        let mut file_out = File::create(outputfile.clone())?;
-        let mut data: chor::ChoreoData = serde_json::from_reader(&mut file)?;
-        data.flip(is_same_alliance);
+        self.plan_renamed_flip(inputfile, outputfile, opts, &HashMap::new(), plan)
+    }
+}
+
+impl ChoreoPlotter {
+    // flips the trajectory as part of an auto, its events following the auto's paths under
+    // the names in `renamed`
+    fn plan_renamed_flip(
+        &self,
+        inputfile: String,
+        outputfile: &Path,
+        opts: &flip::FlipOptions,
+        renamed: &HashMap<String, String>,
+        plan: &mut WritePlan,
+    ) -> Result<()> {
+        let mut loaded = migrate::load_planned::<chor::ChoreoData>(Path::new(&inputfile), plan)?;
+        let data = &mut loaded.data;
+        data.events.iter_mut().for_each(|e| e.rename_paths(renamed));
+        let transform = opts.transform();
+        // there's nothing to mirror when nothing moves, as when only reversing
+        let mut project = if opts.mirror_variables && transform != flip::Transform2d::IDENTITY {
+            chor::ChoreoProject::find(outputfile, plan)?
+        } else {
+            None
+        };
+        calibration::flip_on_venue(data, opts.calibration.as_ref(), |data| {
+            match project.as_mut() {
+                Some(project) => data.flip_with_project(&transform, project),
+                None => data.transform(&transform),
+            }
+        });
+        if let Some(mut project) = project {
+            if let Some(vars) = project.json.get_mut("variables") {
+                util::normalize_json(vars, &opts.precision, false);
+            }
+            plan.modify(&project.path, format::choreo_string(&project.json))?;
+        }
+        if opts.reverse {
+            data.reverse();
+        }
+        if opts.time_scale != 1.0 {
+            data.time_scale(opts.time_scale);
+        }
+        if let Some(dt) = opts.resample_dt {
+            data.resample(dt);
+        }
         data.name = String::from(
-            Path::new(&outputfile)
+            outputfile
                 .file_stem()
                 .and_then(|f| f.to_str())
                 .unwrap_or(""),
         );
-        let new_val = serde_json::to_value(data)?;
-        file_out.write_all(
-            format_custom(&new_val, false, 0)
-                .replace(": ", ":")
-                .as_bytes(),
-        )?;
+        let mut new_val = loaded.to_value(opts.write_latest);
+        util::normalize_json(&mut new_val, &opts.precision, false);
+        plan.output(outputfile, format::choreo_string(&new_val));
         Ok(())
     }
 }
 
 pub struct PathplannerPlotter {
+    arc_lengths: Vec<util::beizer::ArcLength>,
     sample_segs: LinePoints,
     sample_mirr_segs: LinePoints,
     rot_targets: LinePoints,
     rot_targets_mirr: LinePoints,
+    heading_ticks: LinePoints,
+    heading_ticks_mirr: LinePoints,
+    ghost_squares: LinePoints,
+    ghost_squares_mirr: LinePoints,
+    annotations: Vec<Annotation>,
     bg_tex: Option<TextureHandle>,
 }
 
+// a labelled overlay along a path: constraint zones, point towards zones and event
+// markers. Spans with a single point are drawn as a marker instead of a line
+struct Annotation {
+    color: Color32,
+    span: Vec<[f64; 2]>,
+    link: Option<Vec<[f64; 2]>>,
+    label: String,
+    mirrored: bool,
+}
+
 impl Default for PathplannerPlotter {
     fn default() -> Self {
         Self {
+            arc_lengths: Vec::new(),
             sample_segs: Vec::new(),
             sample_mirr_segs: Vec::new(),
             rot_targets: Vec::new(),
             rot_targets_mirr: Vec::new(),
+            heading_ticks: Vec::new(),
+            heading_ticks_mirr: Vec::new(),
+            ghost_squares: Vec::new(),
+            ghost_squares_mirr: Vec::new(),
+            annotations: Vec::new(),
             bg_tex: Option::None,
         }
     }
@@ -285,45 +495,70 @@ impl Default for PathplannerPlotter {
 
 impl Plotter for PathplannerPlotter {
     fn reset(&mut self) {
+        self.arc_lengths.clear();
         self.sample_segs.clear();
         self.sample_mirr_segs.clear();
         self.rot_targets.clear();
         self.rot_targets_mirr.clear();
+        self.heading_ticks.clear();
+        self.heading_ticks_mirr.clear();
+        self.ghost_squares.clear();
+        self.ghost_squares_mirr.clear();
+        self.annotations.clear();
     }
 
     fn share_bg(&mut self, img: Option<TextureHandle>) {
         self.bg_tex = img;
     }
 
-    fn gen(&mut self, filepath: &String, r_ym: f64, r_xm: f64, same_alli: bool) -> Result<()> {
-        use std::fs::File;
-        let file = File::open(filepath)?;
-        let data: pathplanner::path::PathData = serde_json::from_reader(&file)?;
+    fn gen(
+        &mut self,
+        filepath: &Path,
+        r_ym: f64,
+        r_xm: f64,
+        transform: &flip::Transform2d,
+    ) -> Result<()> {
+        let data = migrate::load::<pathplanner::path::PathData>(filepath)?.data;
         let goal_start_state = &data.ideal_starting_state;
         let goal_end_state = &data.goal_end_state;
         let mut gs_flipped = goal_start_state.clone();
-        gs_flipped.flip(same_alli);
+        gs_flipped.transform(transform);
         let mut ge_flipped = goal_end_state.clone();
-        ge_flipped.flip(same_alli);
-        let control_points = &data.waypoints;
+        ge_flipped.transform(transform);
         let rotation_targets = &data.rotation_targets;
         let mut rotation_targets_mirr = rotation_targets.clone();
         rotation_targets_mirr
             .iter_mut()
-            .for_each(|rt| rt.flip(same_alli));
-        let le_anchors: Vec<util::beizer::Anchor> = control_points
-            .iter()
-            .map(|pw| util::beizer::Anchor {
-                position: Vec2d::from_pathpoint(&pw.anchor),
-                control_in: Vec2d::option_from_pathpoint(&pw.prev_control),
-                control_out: Vec2d::option_from_pathpoint(&pw.next_control),
-            })
-            .collect();
+            .for_each(|rt| rt.transform(transform));
+        let le_anchors = data.anchors();
         let mut le_anchors_mirr = le_anchors.clone();
-        le_anchors_mirr.iter_mut().for_each(|a| a.flip(same_alli));
-        let le_samples = util::beizer::beizer_anchors(&le_anchors, 40);
+        le_anchors_mirr
+            .iter_mut()
+            .for_each(|a| a.transform(transform));
+        let mut data_mirr = data.clone();
+        data_mirr.transform(transform);
+        let arc = util::beizer::ArcLength::new(&le_anchors);
+        let arc_mirr = util::beizer::ArcLength::new(&le_anchors_mirr);
+        let (ticks, ghosts) = heading_markers(&data, &arc, r_xm, r_ym);
+        self.heading_ticks.extend(ticks);
+        self.ghost_squares.extend(ghosts);
+        let (ticks, ghosts) = heading_markers(&data_mirr, &arc_mirr, r_xm, r_ym);
+        self.heading_ticks_mirr.extend(ticks);
+        self.ghost_squares_mirr.extend(ghosts);
+        self.annotations
+            .extend(path_annotations(&data, &arc, false));
+        self.annotations
+            .extend(path_annotations(&data_mirr, &arc_mirr, true));
+        let le_samples: Vec<Vec2d> = arc
+            .sample_evenly(SAMPLE_SPACING_M)
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        self.arc_lengths.push(arc);
         let mut le_samples_mirr: Vec<Vec2d> = le_samples.clone();
-        le_samples_mirr.iter_mut().for_each(|s| s.flip(same_alli));
+        le_samples_mirr
+            .iter_mut()
+            .for_each(|s| s.transform(transform));
         for pair in le_samples.windows(2) {
             let s0 = pair[0];
             let s1 = pair[1];
@@ -422,6 +657,35 @@ impl Plotter for PathplannerPlotter {
                     PlotPoint::new(flip::FIELD_X / 2.0, flip::FIELD_Y / 2.0),
                     [flip::FIELD_X as f32, flip::FIELD_Y as f32],
                 ));
+                for pts in &self.ghost_squares {
+                    plot_ui.line(
+                        Line::new("rotation_ghosts", pts.clone())
+                            .color(col.gamma_multiply(0.35))
+                            .width(1.5),
+                    );
+                }
+                for pts in &self.ghost_squares_mirr {
+                    plot_ui.line(
+                        Line::new("rotation_mirr_ghosts", pts.clone())
+                            .color(col.blend(gray_blend).gamma_multiply(0.35))
+                            .style(egui_plot::LineStyle::dashed_dense())
+                            .width(1.5),
+                    );
+                }
+                for pts in &self.heading_ticks {
+                    plot_ui.line(
+                        Line::new("heading_ticks", pts.clone())
+                            .color(*col)
+                            .width(2.0),
+                    );
+                }
+                for pts in &self.heading_ticks_mirr {
+                    plot_ui.line(
+                        Line::new("heading_mirr_ticks", pts.clone())
+                            .color(col.blend(gray_blend))
+                            .width(2.0),
+                    );
+                }
                 for pts in &self.rot_targets {
                     let p0 = Vec2d::from_array(pts[0]);
                     let p3 = Vec2d::from_array(pts[3]);
@@ -474,63 +738,123 @@ impl Plotter for PathplannerPlotter {
                             .width(4.0),
                     );
                 }
+                draw_annotations(plot_ui, &self.annotations, gray_blend2);
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    let pointer = Vec2d::new(pointer.x, pointer.y);
+                    let hovered = self
+                        .arc_lengths
+                        .iter()
+                        .map(|arc| (arc, arc.nearest(pointer)))
+                        .min_by(|a, b| a.1.offset.total_cmp(&b.1.offset));
+                    if let Some((arc, near)) = hovered.filter(|h| h.1.offset < HOVER_RADIUS_M) {
+                        let seg_lengths = arc.segment_lengths();
+                        let seg_idx = (near.t.floor() as usize).min(seg_lengths.len() - 1);
+                        plot_ui.points(
+                            Points::new("hover_point", vec![near.point.to_array()])
+                                .color(Color32::WHITE)
+                                .radius(5.0),
+                        );
+                        plot_ui.text(
+                            Text::new(
+                                "hover_readout",
+                                PlotPoint::new(near.point.x, near.point.y + 0.2),
+                                format!(
+                                    "{:.2} / {:.2} m (segment {}: {:.2} m)\nheading {:.1}\u{b0}  curvature {:.2} 1/m",
+                                    near.distance_along,
+                                    arc.total_length(),
+                                    seg_idx + 1,
+                                    seg_lengths[seg_idx],
+                                    arc.heading_at_distance(near.distance_along).to_degrees(),
+                                    arc.curvature_at_distance(near.distance_along),
+                                ),
+                            )
+                            .color(Color32::WHITE)
+                            .anchor(egui::Align2::LEFT_BOTTOM),
+                        );
+                    }
+                }
             });
 
         Ok(())
     }
 
-    fn send_flip(
+    fn plan_flip(
         &self,
         inputfile: String,
-        outputfile: String,
-        is_same_alliance: bool,
-        _: Option<&Vec<String>>,
+        outputfile: &Path,
+        opts: &flip::FlipOptions,
+        _: Option<&[PathBuf]>,
+        plan: &mut WritePlan,
     ) -> Result<()> {
-        let mut file = File::open(&inputfile)?;
-        let mut file_out = File::create(outputfile.clone())?;
-        let mut data: pathplanner::path::PathData = serde_json::from_reader(&mut file)?;
-        data.flip(is_same_alliance);
-        data.folder = Some("Flipped".to_owned());
-        let new_val = serde_json::to_value(data)?;
-        file_out.write_all(format_pretty(&new_val).as_bytes())?;
-        Self::add_folder(
-            Path::new(&inputfile)
-                .parent()
-                .unwrap()
-                .parent()
-                .unwrap()
-                .join("settings.json"),
-            Some("Flipped"),
-            None,
-        )?;
+        let mut loaded =
+            migrate::load_planned::<pathplanner::path::PathData>(Path::new(&inputfile), plan)?;
+        // linked waypoints that stay where they are, as when only reversing, stay linked
+        let transform = opts.transform();
+        if transform != flip::Transform2d::IDENTITY {
+            calibration::flip_on_venue(&mut loaded.data, opts.calibration.as_ref(), |data| {
+                data.transform(&transform)
+            });
+        }
+        if opts.reverse {
+            loaded.data.reverse();
+        }
+        loaded.data.folder = Some("Flipped".to_owned());
+        let mut new_val = loaded.to_value(opts.write_latest);
+        util::normalize_json(&mut new_val, &opts.precision, true);
+        plan.output(outputfile, format::pathplanner_string(&new_val));
+        Self::add_folder(settings_path(&inputfile)?, Some("Flipped"), None, plan)?;
         Ok(())
     }
 }
 
+fn settings_path(inputfile: &str) -> Result<PathBuf> {
+    let input = Path::new(inputfile);
+    project::Layout::discover(input).settings(input)
+}
+
+// autos and events refer to their paths by file name
+fn renamed_stems(path_flips: &[(PathBuf, PathBuf)]) -> HashMap<String, String> {
+    let stem = |p: &PathBuf| {
+        p.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    path_flips
+        .iter()
+        .map(|(source, output)| (stem(source), stem(output)))
+        .collect()
+}
+
 impl PathplannerPlotter {
-    fn send_auto_flip(
+    fn plan_auto_flip(
         &self,
         inputfile: String,
-        outputfile: String,
-        auto_file_names: &Vec<String>,
+        outputfile: &Path,
+        path_flips: &[(PathBuf, PathBuf)],
+        opts: &flip::FlipOptions,
+        plan: &mut WritePlan,
     ) -> Result<()> {
-        let mut file = File::open(&inputfile)?;
-        let mut file_out = File::create(outputfile.clone())?;
-        let mut data: pathplanner::auto::AutoData = serde_json::from_reader(&mut file)?;
+        let mut loaded =
+            migrate::load_planned::<pathplanner::auto::AutoData>(Path::new(&inputfile), plan)?;
+        let data = &mut loaded.data;
         data.folder = Some("Flipped".to_owned());
-        data.command = data.command.replace_path_commands(auto_file_names);
-        let new_val = serde_json::to_value(data)?;
-        file_out.write_all(format_pretty(&new_val).as_bytes())?;
-        Self::add_folder(
-            Path::new(&inputfile)
-                .parent()
-                .unwrap()
-                .parent()
-                .unwrap()
-                .join("settings.json"),
-            None,
-            Some("Flipped"),
-        )?;
+        data.rename_paths(&renamed_stems(path_flips))
+            .map_err(|name| {
+                Error::layout(&inputfile, format!("{} wasn't flipped with the auto", name))
+            })?;
+        calibration::flip_on_venue(data, opts.calibration.as_ref(), |data| {
+            if let Some(pose) = data.starting_pose.as_mut() {
+                pose.transform(&opts.transform());
+            }
+        });
+        if opts.write_latest {
+            data.starting_pose = None;
+        }
+        let mut new_val = loaded.to_value(opts.write_latest);
+        util::normalize_json(&mut new_val, &opts.precision, true);
+        plan.output(outputfile, format::pathplanner_string(&new_val));
+        Self::add_folder(settings_path(&inputfile)?, None, Some("Flipped"), plan)?;
         Ok(())
     }
 
@@ -538,9 +862,12 @@ impl PathplannerPlotter {
         path: PathBuf,
         path_folder: Option<&str>,
         auto_folder: Option<&str>,
+        plan: &mut WritePlan,
     ) -> Result<()> {
-        let data = fs::read_to_string(&path)?;
-        let mut json: Value = serde_json::from_str(&data)?;
+        let data = plan.read(&path)?;
+        let mut json: Value =
+            serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&data))
+                .map_err(|e| Error::parse(&path, e))?;
 
         let mut add_unique = |key: &str, val: &str| {
             if let Some(arr) = json.get_mut(key).and_then(|v| v.as_array_mut()) {
@@ -558,11 +885,7 @@ impl PathplannerPlotter {
             add_unique("autoFolders", a);
         }
 
-        let mut buf = Vec::new();
-        let formatter = PrettyFormatter::with_indent(b"    ");
-        let mut serializer = Serializer::with_formatter(&mut buf, formatter);
-        json.serialize(&mut serializer)?;
-        fs::write(path, buf)?;
+        plan.modify(&path, format::pathplanner_string(&json))?;
         Ok(())
     }
 }
@@ -595,22 +918,26 @@ impl Plotter for DualPlotter {
 
     fn share_bg(&mut self, _: Option<TextureHandle>) {}
 
-    fn gen(&mut self, filepath: &String, r_xm: f64, r_ym: f64, same_alliance: bool) -> Result<()> {
+    fn gen(
+        &mut self,
+        filepath: &Path,
+        r_xm: f64,
+        r_ym: f64,
+        transform: &flip::Transform2d,
+    ) -> Result<()> {
         match self.plot_type {
-            FlipFileType::Choreo => self.choreo.gen(filepath, r_xm, r_ym, same_alliance),
-            FlipFileType::Pathplanner => self.pathplanner.gen(filepath, r_xm, r_ym, same_alliance),
+            FlipFileType::Choreo => self.choreo.gen(filepath, r_xm, r_ym, transform),
+            FlipFileType::Pathplanner => self.pathplanner.gen(filepath, r_xm, r_ym, transform),
             FlipFileType::PathplannerAuto { is_chor: false } => {
                 for path in &self.auto_files {
-                    self.pathplanner
-                        .gen(&path.display().to_string(), r_xm, r_ym, same_alliance)?;
+                    self.pathplanner.gen(path, r_xm, r_ym, transform)?;
                 }
 
                 Ok(())
             }
             FlipFileType::PathplannerAuto { is_chor: true } => {
                 for path in &self.auto_files {
-                    self.choreo
-                        .gen(&path.display().to_string(), r_xm, r_ym, same_alliance)?;
+                    self.choreo.gen(path, r_xm, r_ym, transform)?;
                 }
 
                 Ok(())
@@ -623,9 +950,11 @@ impl Plotter for DualPlotter {
             let img = image::ImageReader::new(std::io::Cursor::new(include_bytes!(
                 "../images/field.png"
             )))
-            .with_guessed_format()?
+            .with_guessed_format()
+            .with_path("images/field.png")?
             .decode()
-            .unwrap();
+            .map_err(std::io::Error::other)
+            .with_path("images/field.png")?;
             let size = [img.width() as usize, img.height() as usize];
             let img_buff = img.to_rgba8();
             let pix = img_buff.as_flat_samples();
@@ -647,75 +976,162 @@ impl Plotter for DualPlotter {
         }
     }
 
-    fn send_flip(
+    fn plan_flip(
         &self,
         inputfile: String,
-        outputfile: String,
-        is_same_alliance: bool,
-        auto_file_names: Option<&Vec<String>>,
+        outputfile: &Path,
+        opts: &flip::FlipOptions,
+        auto_outputs: Option<&[PathBuf]>,
+        plan: &mut WritePlan,
     ) -> Result<()> {
         match self.plot_type {
-            FlipFileType::Choreo => {
-                self.choreo
-                    .send_flip(inputfile, outputfile, is_same_alliance, Option::None)
-            }
-            FlipFileType::Pathplanner => {
-                self.pathplanner
-                    .send_flip(inputfile, outputfile, is_same_alliance, auto_file_names)
-            }
-            FlipFileType::PathplannerAuto { is_chor: false } => {
-                if let Some(afn_arr) = auto_file_names {
-                    for (i, path) in self.auto_files.iter().enumerate() {
-                        self.pathplanner.send_flip(
-                            path.display().to_string(),
-                            self.auto_files[0]
-                                .parent()
-                                .unwrap()
-                                .join(afn_arr[i].clone())
-                                .with_extension("path")
-                                .display()
-                                .to_string(),
-                            is_same_alliance,
-                            Option::None,
-                        )?;
+            FlipFileType::Choreo => self
+                .choreo
+                .plan_flip(inputfile, outputfile, opts, None, plan),
+            FlipFileType::Pathplanner => self
+                .pathplanner
+                .plan_flip(inputfile, outputfile, opts, None, plan),
+            // the auto would have to run its commands backwards too
+            FlipFileType::PathplannerAuto { .. } if opts.reverse => Err(Error::layout(
+                inputfile,
+                "autos can't be reversed, only the paths and trajectories in them",
+            )),
+            FlipFileType::PathplannerAuto { is_chor } => {
+                let path_flips: Vec<(PathBuf, PathBuf)> = self
+                    .auto_files
+                    .iter()
+                    .cloned()
+                    .zip(auto_outputs.unwrap_or_default().iter().cloned())
+                    .collect();
+                let renamed = renamed_stems(&path_flips);
+                for (path, output) in &path_flips {
+                    let path = path.display().to_string();
+                    if is_chor {
+                        self.choreo
+                            .plan_renamed_flip(path, output, opts, &renamed, plan)?;
+                    } else {
+                        self.pathplanner.plan_flip(path, output, opts, None, plan)?;
                     }
                 }
+                self.pathplanner
+                    .plan_auto_flip(inputfile, outputfile, &path_flips, opts, plan)
+            }
+        }
+    }
+}
 
-                self.pathplanner.send_auto_flip(
-                    inputfile,
-                    outputfile,
-                    auto_file_names.unwrap_or(&Vec::new()),
-                )
+impl DualPlotter {
+    // problems found while generating the preview that should be confirmed before flipping
+    pub fn warnings(&self) -> &[String] {
+        match self.plot_type {
+            FlipFileType::Choreo | FlipFileType::PathplannerAuto { is_chor: true } => {
+                &self.choreo.warnings
             }
-            FlipFileType::PathplannerAuto { is_chor: true } => {
-                if let Some(afn_arr) = auto_file_names {
-                    for (i, path) in self.auto_files.iter().enumerate() {
-                        self.choreo.send_flip(
-                            path.display().to_string(),
-                            self.auto_files[0]
-                                .parent()
-                                .unwrap()
-                                .join(afn_arr[i].clone())
-                                .with_extension("traj")
-                                .display()
-                                .to_string(),
-                            is_same_alliance,
-                            Option::None,
-                        )?;
-                    }
-                }
+            FlipFileType::Pathplanner | FlipFileType::PathplannerAuto { is_chor: false } => &[],
+        }
+    }
 
-                self.pathplanner.send_auto_flip(
-                    inputfile,
-                    outputfile,
-                    auto_file_names.unwrap_or(&Vec::new()),
+    // links where the flip puts each waypoint and sample of the compared file to where
+    // that file has it
+    pub fn show_deviations(&mut self, comparison: &symmetry::Comparison) {
+        let deviation = |d: &symmetry::Deviation, label: String| Annotation {
+            color: color_lerp(
+                Color32::GREEN,
+                Color32::RED,
+                d.position() / DEVIATION_FULL_M,
+            ),
+            span: vec![[d.expected.x, d.expected.y], [d.actual.x, d.actual.y]],
+            link: None,
+            label,
+            mirrored: false,
+        };
+        let annotations = comparison
+            .waypoints
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                deviation(
+                    d,
+                    format!(
+                        "{}: {:.3} m, {:.1}\u{b0}",
+                        i + 1,
+                        d.position(),
+                        d.heading().to_degrees()
+                    ),
                 )
+            })
+            .chain(
+                comparison
+                    .samples
+                    .iter()
+                    .filter(|d| d.position() > DEVIATION_MIN_M)
+                    .map(|d| deviation(d, String::new())),
+            );
+        match self.plot_type {
+            FlipFileType::Choreo | FlipFileType::PathplannerAuto { is_chor: true } => {
+                self.choreo.events.extend(annotations)
+            }
+            FlipFileType::Pathplanner | FlipFileType::PathplannerAuto { is_chor: false } => {
+                self.pathplanner.annotations.extend(annotations)
+            }
+        }
+    }
+
+    // draws the route of every variant over the preview, each in its own color and
+    // labelled halfway along its first path
+    pub fn show_variants(&mut self, variants: &[(String, flip::Transform2d)]) {
+        let routes: Vec<Vec<Vec2d>> = match self.plot_type {
+            FlipFileType::Choreo | FlipFileType::PathplannerAuto { is_chor: true } => self
+                .choreo
+                .trajs
+                .iter()
+                .map(|(traj, _)| traj.samples.iter().map(|s| Vec2d::new(s.x, s.y)).collect())
+                .collect(),
+            FlipFileType::Pathplanner | FlipFileType::PathplannerAuto { is_chor: false } => self
+                .pathplanner
+                .arc_lengths
+                .iter()
+                .map(|arc| {
+                    arc.sample_evenly(SAMPLE_SPACING_M)
+                        .into_iter()
+                        .map(|(_, p)| p)
+                        .collect()
+                })
+                .collect(),
+        };
+        let mut annotations = Vec::new();
+        for ((label, transform), color) in variants.iter().zip(VARIANT_COLORS.iter().cycle()) {
+            for (i, route) in routes.iter().enumerate() {
+                let span: Vec<[f64; 2]> = route
+                    .iter()
+                    .map(|p| {
+                        let mut p = *p;
+                        p.transform(transform);
+                        p.to_array()
+                    })
+                    .collect();
+                if span.is_empty() {
+                    continue;
+                }
+                annotations.push(Annotation {
+                    color: *color,
+                    link: None,
+                    label: if i == 0 { label.clone() } else { String::new() },
+                    span,
+                    mirrored: false,
+                });
+            }
+        }
+        match self.plot_type {
+            FlipFileType::Choreo | FlipFileType::PathplannerAuto { is_chor: true } => {
+                self.choreo.events.extend(annotations)
+            }
+            FlipFileType::Pathplanner | FlipFileType::PathplannerAuto { is_chor: false } => {
+                self.pathplanner.annotations.extend(annotations)
             }
         }
     }
-}
 
-impl DualPlotter {
     pub fn set_plot_type(&mut self, plot_type: &FlipFileType, paths: Vec<PathBuf>) {
         self.plot_type = *plot_type;
         if paths.len() > 0 {
@@ -733,6 +1149,162 @@ fn color_lerp(low: egui::Color32, high: egui::Color32, t: f64) -> egui::Color32
     )
 }
 
+fn draw_annotations(
+    plot_ui: &mut egui_plot::PlotUi,
+    annotations: &[Annotation],
+    gray_blend: Color32,
+) {
+    for annotation in annotations {
+        let color = if annotation.mirrored {
+            annotation.color.blend(gray_blend)
+        } else {
+            annotation.color
+        };
+        if let Some(link) = &annotation.link {
+            plot_ui.line(
+                Line::new("annotation_link", link.clone())
+                    .color(color)
+                    .style(egui_plot::LineStyle::dashed_loose())
+                    .width(1.5),
+            );
+        }
+        if annotation.span.len() > 1 {
+            plot_ui.line(
+                Line::new("annotation_span", annotation.span.clone())
+                    .color(color.gamma_multiply(0.6))
+                    .width(9.0),
+            );
+        } else {
+            plot_ui.points(
+                Points::new("annotation_point", annotation.span.clone())
+                    .color(color)
+                    .shape(egui_plot::MarkerShape::Diamond)
+                    .radius(6.0),
+            );
+        }
+        let label_at = annotation.span[annotation.span.len() / 2];
+        plot_ui.text(
+            Text::new(
+                "annotation_label",
+                PlotPoint::new(label_at[0], label_at[1]),
+                &annotation.label,
+            )
+            .color(color)
+            .anchor(egui::Align2::LEFT_TOP),
+        );
+    }
+}
+
+fn span_points(arc: &util::beizer::ArcLength, min_pos: f64, max_pos: f64) -> Vec<[f64; 2]> {
+    let s0 = arc.distance_at(min_pos);
+    let s1 = arc.distance_at(max_pos);
+    let count = ((s1 - s0) / SAMPLE_SPACING_M).ceil().max(1.0) as usize;
+    (0..=count)
+        .map(|i| {
+            arc.point_at_distance(s0 + (s1 - s0) * i as f64 / count as f64)
+                .to_array()
+        })
+        .collect()
+}
+
+fn path_annotations(
+    data: &pathplanner::path::PathData,
+    arc: &util::beizer::ArcLength,
+    mirrored: bool,
+) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    for (i, zone) in data.constraint_zones.iter().enumerate() {
+        let c = &zone.constraints;
+        let limits = if c.unlimited {
+            "unlimited".to_owned()
+        } else {
+            format!(
+                "{:.2} m/s, {:.2} m/s\u{b2}, {:.0}\u{b0}/s, {:.0}\u{b0}/s\u{b2}",
+                c.max_velocity,
+                c.max_acceleration,
+                c.max_angular_velocity,
+                c.max_angular_acceleration
+            )
+        };
+        annotations.push(Annotation {
+            color: ZONE_COLORS[i % ZONE_COLORS.len()],
+            span: span_points(
+                arc,
+                zone.min_waypoint_relative_pos,
+                zone.max_waypoint_relative_pos,
+            ),
+            link: None,
+            label: format!("{}: {}", zone.name, limits),
+            mirrored,
+        });
+    }
+    for zone in &data.point_towards_zones {
+        let span = span_points(
+            arc,
+            zone.min_waypoint_relative_pos,
+            zone.max_waypoint_relative_pos,
+        );
+        let target = [zone.field_position.x, zone.field_position.y];
+        annotations.push(Annotation {
+            color: Color32::from_rgb(0, 200, 200),
+            link: Some(vec![span[span.len() / 2], target]),
+            span,
+            label: format!("{} ({:+.0}\u{b0})", zone.name, zone.rotation_offset),
+            mirrored,
+        });
+    }
+    for marker in &data.event_markers {
+        let commands = marker
+            .command
+            .as_ref()
+            .map(|c| c.named_commands())
+            .unwrap_or_default();
+        let span = match marker.end_waypoint_relative_pos {
+            Some(end) => span_points(arc, marker.waypoint_relative_pos, end),
+            None => {
+                vec![util::beizer::point_at(arc.anchors(), marker.waypoint_relative_pos).to_array()]
+            }
+        };
+        annotations.push(Annotation {
+            color: Color32::YELLOW,
+            span,
+            link: None,
+            label: if commands.is_empty() {
+                marker.name.clone()
+            } else {
+                format!("{} [{}]", marker.name, commands.join(", "))
+            },
+            mirrored,
+        });
+    }
+    annotations
+}
+
+// short heading ticks along the path plus faint robot footprints at a wider spacing,
+// following the interpolated holonomic rotation
+fn heading_markers(
+    data: &pathplanner::path::PathData,
+    arc: &util::beizer::ArcLength,
+    r_xm: f64,
+    r_ym: f64,
+) -> (LinePoints, LinePoints) {
+    let tick_len = r_xm.min(r_ym) / 2.0;
+    let ticks = data
+        .rotation_profile(arc, HEADING_TICK_SPACING_M)
+        .into_iter()
+        .map(|(p, rot)| {
+            let tip = p.add(Vec2d::new(rot.cos(), rot.sin()).scale(tick_len));
+            vec![p.to_array(), tip.to_array()]
+        })
+        .collect();
+    let ghosts = data
+        .rotation_profile(arc, GHOST_SPACING_M)
+        .into_iter()
+        .map(|(p, rot)| draw_rotate_square_rect(p.to_array(), r_xm, r_ym, rot))
+        .collect();
+    (ticks, ghosts)
+}
+
 fn draw_rotate_square_rect(center: [f64; 2], width: f64, height: f64, angle: f64) -> Vec<[f64; 2]> {
     let half_width = width / 2.0;
     let half_height = height / 2.0;
@@ -759,75 +1331,90 @@ fn draw_rotate_square_rect(center: [f64; 2], width: f64, height: f64, angle: f64
     return closed_corners;
 }
 
-fn format_custom(value: &serde_json::Value, in_array: bool, indent: usize) -> String {
-    match value {
-        serde_json::Value::Object(map) => {
-            if in_array {
-                let entries = map
-                    .iter()
-                    .map(|(k, v)| format!("\"{}\": {}", k, format_custom(v, true, indent)))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("{{{}}}", entries)
-            } else {
-                let indent_str = " ".repeat(indent);
-                let inner_indent_str = " ".repeat(indent + 1);
-                let entries = map
-                    .iter()
-                    .map(|(k, v)| {
-                        format!(
-                            "{}\"{}\": {}",
-                            inner_indent_str,
-                            k,
-                            format_custom(v, false, indent + 1)
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join(",\n");
-                format!("{{\n{}\n{}}}", entries, indent_str)
-            }
-        }
-        serde_json::Value::Array(arr) => {
-            if in_array {
-                let items: Vec<String> =
-                    arr.iter().map(|v| format_custom(v, true, indent)).collect();
-                format!("[{}]", items.join(","))
-            } else {
-                let inner_indent_str = "  ".repeat(indent);
-                let all_prim: bool = arr.iter().all(|f| {
-                    matches!(
-                        f,
-                        serde_json::Value::Null
-                            | serde_json::Value::Bool(_)
-                            | serde_json::Value::Number(_)
-                            | serde_json::Value::String(_)
-                    )
-                });
-                let entries = arr
-                    .iter()
-                    .map(|v| {
-                        format!(
-                            "{}{}",
-                            if all_prim { "" } else { &inner_indent_str },
-                            format_custom(v, true, indent + 1)
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join(if all_prim { "," } else { ",\n" });
-                format!("[{}{}]", if all_prim { "" } else { "\n" }, entries)
-            }
-        }
-        _ => value.to_string(),
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn format_pretty(value: &Value) -> String {
-    let mut buf = Vec::new();
+    // a straight 4 m path with evenly spaced controls, so relative position 0.25 is 1 m along
+    fn path() -> pathplanner::path::PathData {
+        let constraints = serde_json::json!({
+            "maxVelocity": 1.0,
+            "maxAcceleration": 2.0,
+            "maxAngularVelocity": 90.0,
+            "maxAngularAcceleration": 180.0,
+            "nominalVoltage": 12.0,
+            "unlimited": false
+        });
+        let point = |x: f64| serde_json::json!({ "x": x, "y": 1.0 });
+        serde_json::from_value(serde_json::json!({
+            "version": "2025.0",
+            "waypoints": [
+                { "anchor": point(0.0), "prevControl": null, "nextControl": point(4.0 / 3.0), "isLocked": false, "linkedName": null },
+                { "anchor": point(4.0), "prevControl": point(8.0 / 3.0), "nextControl": null, "isLocked": false, "linkedName": null }
+            ],
+            "rotationTargets": [],
+            "constraintZones": [{
+                "name": "slow",
+                "minWaypointRelativePos": 0.25,
+                "maxWaypointRelativePos": 0.75,
+                "constraints": constraints
+            }],
+            "pointTowardsZones": [{
+                "fieldPosition": { "x": 2.0, "y": 3.0 },
+                "rotationOffset": 180.0,
+                "minWaypointRelativePos": 0.5,
+                "maxWaypointRelativePos": 1.0,
+                "name": "speaker"
+            }],
+            "eventMarkers": [
+                { "name": "intake", "waypointRelativePos": 0.0, "endWaypointRelativePos": 0.5, "command": null },
+                {
+                    "name": "shoot",
+                    "waypointRelativePos": 0.5,
+                    "endWaypointRelativePos": null,
+                    "command": { "type": "named", "data": { "name": "fire" } }
+                }
+            ],
+            "globalConstraints": constraints,
+            "goalEndState": { "velocity": 0.0, "rotation": 0.0 },
+            "reversed": false,
+            "folder": null,
+            "idealStartingState": { "velocity": 0.0, "rotation": 0.0 },
+            "useDefaultConstraints": true
+        }))
+        .unwrap()
+    }
 
-    let formatter = PrettyFormatter::with_indent(b"    "); // 4 spaces
-    let mut serializer = Serializer::with_formatter(&mut buf, formatter);
+    fn near(a: [f64; 2], b: [f64; 2]) -> bool {
+        Vec2d::from_array(a).dist(Vec2d::from_array(b)) < 1e-3
+    }
 
-    value.serialize(&mut serializer).unwrap();
+    #[test]
+    fn zones_and_markers_are_drawn_where_they_act() {
+        let data = path();
+        let arc = util::beizer::ArcLength::new(&data.anchors());
+        let annotations = path_annotations(&data, &arc, true);
+        assert!(annotations.iter().all(|a| a.mirrored));
+        let labels: Vec<&str> = annotations.iter().map(|a| a.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "slow: 1.00 m/s, 2.00 m/s\u{b2}, 90\u{b0}/s, 180\u{b0}/s\u{b2}",
+                "speaker (+180\u{b0})",
+                "intake",
+                "shoot [fire]",
+            ]
+        );
 
-    String::from_utf8(buf).unwrap()
+        let zone = &annotations[0].span;
+        assert!(near(zone[0], [1.0, 1.0]) && near(zone[zone.len() - 1], [3.0, 1.0]));
+        // the point towards zone is linked from its middle to what it faces
+        let facing = annotations[1].link.as_ref().unwrap();
+        assert!(near(facing[0], [3.0, 1.0]) && near(facing[1], [2.0, 3.0]));
+        let intake = &annotations[2].span;
+        assert!(near(intake[0], [0.0, 1.0]) && near(intake[intake.len() - 1], [2.0, 1.0]));
+        // a marker without an end is a single point
+        assert_eq!(annotations[3].span.len(), 1);
+        assert!(near(annotations[3].span[0], [2.0, 1.0]));
+    }
 }
@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::lib::error::{Error, Result};
+
+// where a robot project keeps its choreo and pathplanner files, found by walking up from
+// a file that was opened. The usual layout is
+//   deploy/choreo/<project>.chor and *.traj
+//   deploy/pathplanner/settings.json, paths/*.path and autos/*.auto
+// but each tool's folder is also recognised on its own, wherever it sits
+#[derive(Default, Debug)]
+pub struct Layout {
+    pub deploy: Option<PathBuf>,
+    // the folder holding settings.json
+    pub pathplanner: Option<PathBuf>,
+    // the .chor project file
+    pub choreo: Option<PathBuf>,
+}
+
+impl Layout {
+    pub fn discover(file: &Path) -> Layout {
+        let mut layout = Layout::default();
+        // relative paths have no parents to search past the working directory
+        let file = absolute(file);
+        for dir in file.ancestors().skip(1) {
+            if layout.pathplanner.is_none() {
+                layout.pathplanner = [dir.to_path_buf(), dir.join("pathplanner")]
+                    .into_iter()
+                    .find(|d| is_pathplanner_dir(d));
+            }
+            if layout.choreo.is_none() {
+                layout.choreo = find_chor(dir).or_else(|| find_chor(&dir.join("choreo")));
+            }
+            if dir.file_name().is_some_and(|n| n == "deploy") {
+                layout.deploy = Some(dir.to_path_buf());
+                break;
+            }
+            if layout.pathplanner.is_some() && layout.choreo.is_some() {
+                break;
+            }
+        }
+        layout
+    }
+
+    // the settings.json for a pathplanner file, `file` is only used to report errors
+    pub fn settings(&self, file: &Path) -> Result<PathBuf> {
+        self.pathplanner
+            .as_ref()
+            .map(|dir| dir.join("settings.json"))
+            .ok_or(Error::layout(
+                file,
+                "no pathplanner settings.json in this folder or any folder above it",
+            ))
+    }
+
+    // the files of the paths or trajectories an auto follows, all of which have to exist
+    pub fn auto_paths(&self, auto: &Path, names: &[String], is_chor: bool) -> Result<Vec<PathBuf>> {
+        let dir = if is_chor {
+            self.choreo
+                .as_ref()
+                .and_then(|chor| chor.parent().map(Path::to_path_buf))
+                .or_else(|| self.deploy.as_ref().map(|d| d.join("choreo")))
+                .ok_or(Error::layout(
+                    auto,
+                    "no choreo project (.chor) in this folder or any folder above it",
+                ))?
+        } else {
+            self.settings(auto)?.with_file_name("paths")
+        };
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                if path.is_file() {
+                    Ok(path)
+                } else {
+                    Err(Error::layout(
+                        auto,
+                        format!("follows {}, which isn't in {}", name, dir.display()),
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+// `path` from the root, without any "." or ".." in it
+pub fn absolute(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn is_pathplanner_dir(dir: &Path) -> bool {
+    dir.join("settings.json").is_file()
+        && (dir.join("paths").is_dir() || dir.join("autos").is_dir())
+}
+
+fn find_chor(dir: &Path) -> Option<PathBuf> {
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "chor"))
+        .collect();
+    // read_dir order isn't stable, pick the same one every time
+    found.sort();
+    found.into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::fixtures;
+
+    #[test]
+    fn layout_is_found_above_the_file() {
+        let deploy = fixtures::project("layout");
+        fs::create_dir_all(deploy.join("choreo")).unwrap();
+        for chor in ["B.chor", "A.chor"] {
+            fs::write(deploy.join("choreo").join(chor), "{}").unwrap();
+        }
+        let path = fixtures::write_path(&deploy, "Start", &[[1.0, 1.0], [2.0, 1.0]]);
+
+        let layout = Layout::discover(&path);
+        assert_eq!(layout.deploy.as_deref(), Some(deploy.as_path()));
+        assert_eq!(layout.pathplanner, Some(deploy.join("pathplanner")));
+        assert_eq!(layout.choreo, Some(deploy.join("choreo/A.chor")));
+        assert_eq!(
+            layout.settings(&path).unwrap(),
+            deploy.join("pathplanner/settings.json")
+        );
+
+        let auto = fixtures::write_auto(&deploy, "Auto", &["Start"]);
+        let names = ["Start.path".to_owned(), "Missing.path".to_owned()];
+        assert_eq!(
+            layout.auto_paths(&auto, &names[..1], false).unwrap(),
+            vec![path]
+        );
+        assert!(layout.auto_paths(&auto, &names, false).is_err());
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn a_pathplanner_folder_is_found_on_its_own() {
+        let deploy = fixtures::project("loose-layout");
+        // without a deploy folder above it, and reached through a relative part
+        let folder = deploy.with_file_name("robot");
+        fs::rename(&deploy, &folder).unwrap();
+        let path = folder.join("pathplanner/paths/../autos/./Auto.auto");
+
+        let layout = Layout::discover(&path);
+        assert_eq!(layout.deploy, None);
+        assert_eq!(layout.pathplanner, Some(folder.join("pathplanner")));
+        assert_eq!(layout.choreo, None);
+        fixtures::remove(&folder);
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::lib::{
+    chor,
+    error::{Error, Result},
+    flip::{Field, Flippable, Transform2d},
+    manifest, migrate,
+    pathplanner::path::PathData,
+    util::{self, beizer::ArcLength, Pose2d, Vec2d},
+};
+
+// checks whether one existing path or trajectory is a mirror of another, for mirrors made
+// by hand rather than by flipping. Both files are compared as poses, by waypoint and
+// along the route, against each way the field can be mirrored
+
+const SAMPLE_SPACING_M: f64 = 0.05;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Symmetry {
+    // across the field's long axis, from one side of an alliance's wall to the other
+    SameAlliance,
+    // across the center line, onto the other alliance's half
+    Alliance,
+    // turned half way around the field's center
+    Rotational,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 3] = [
+        Symmetry::SameAlliance,
+        Symmetry::Alliance,
+        Symmetry::Rotational,
+    ];
+
+    pub fn transform(self, field: &Field) -> Transform2d {
+        match self {
+            Symmetry::SameAlliance => Transform2d::same_alliance(field),
+            Symmetry::Alliance => Transform2d::mirrored_alliance(field),
+            Symmetry::Rotational => Transform2d::rotational(field),
+        }
+    }
+}
+
+// where the mirror puts a pose of the first file, and where the second file has it
+pub struct Deviation {
+    pub expected: Pose2d,
+    pub actual: Pose2d,
+}
+
+impl Deviation {
+    pub fn position(&self) -> f64 {
+        self.expected.translation().dist(self.actual.translation())
+    }
+
+    // in radians
+    pub fn heading(&self) -> f64 {
+        util::wrap_angle(self.actual.heading - self.expected.heading).abs()
+    }
+}
+
+pub struct Comparison {
+    pub symmetry: Symmetry,
+    pub waypoints: Vec<Deviation>,
+    // evenly along the route, by fraction of its length or duration
+    pub samples: Vec<Deviation>,
+}
+
+impl Comparison {
+    // the largest position and heading errors, which needn't come from the same pose
+    pub fn max_waypoint_error(&self) -> (f64, f64) {
+        max_errors(&self.waypoints)
+    }
+
+    pub fn max_sample_error(&self) -> (f64, f64) {
+        max_errors(&self.samples)
+    }
+
+    fn max_position(&self) -> f64 {
+        self.max_waypoint_error().0.max(self.max_sample_error().0)
+    }
+}
+
+fn max_errors(deviations: &[Deviation]) -> (f64, f64) {
+    deviations.iter().fold((0.0, 0.0), |(p, h), d| {
+        (p.max(d.position()), h.max(d.heading()))
+    })
+}
+
+pub struct Report {
+    pub source: PathBuf,
+    pub mirror: PathBuf,
+    // one for each symmetry, closest match first
+    pub comparisons: Vec<Comparison>,
+}
+
+impl Report {
+    // `compare` always compares every symmetry, so there's a first
+    pub fn best(&self) -> &Comparison {
+        &self.comparisons[0]
+    }
+}
+
+// compares `mirror` against every symmetry of `source`, about the center of the field its
+// project is calibrated to, or the mirror's when only that one is. Both have to be the
+// same kind of file with the same number of waypoints
+pub fn compare(source: &Path, mirror: &Path) -> Result<Report> {
+    let a = Route::load(source)?;
+    let b = Route::load(mirror)?;
+    if std::mem::discriminant(&a) != std::mem::discriminant(&b) {
+        return Err(Error::layout(
+            mirror,
+            format!("isn't the same kind of file as {}", source.display()),
+        ));
+    }
+    let (a_wps, b_wps) = (a.waypoints(), b.waypoints());
+    if a_wps.len() != b_wps.len() {
+        return Err(Error::schema(
+            mirror,
+            format!(
+                "has {} waypoints and {} has {}, so neither can be a mirror of the other",
+                b_wps.len(),
+                source.display(),
+                a_wps.len()
+            ),
+        ));
+    }
+    let samples = a.samples();
+    let profile = match manifest::calibration(source)? {
+        Some(profile) => Some(profile),
+        None => manifest::calibration(mirror)?,
+    };
+    let field = profile.map_or(Field::NOMINAL, |p| p.field());
+    let mut comparisons: Vec<Comparison> = Symmetry::ALL
+        .into_iter()
+        .map(|symmetry| (symmetry, symmetry.transform(&field)))
+        .map(|(symmetry, transform)| Comparison {
+            symmetry,
+            waypoints: a_wps
+                .iter()
+                .zip(&b_wps)
+                .map(|(a, b)| Deviation {
+                    expected: apply(&transform, *a),
+                    actual: *b,
+                })
+                .collect(),
+            samples: samples
+                .iter()
+                .filter_map(|(progress, pose)| {
+                    Some(Deviation {
+                        expected: apply(&transform, *pose),
+                        actual: b.pose_at(*progress)?,
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+    comparisons.sort_by(|x, y| x.max_position().total_cmp(&y.max_position()));
+    Ok(Report {
+        source: source.to_path_buf(),
+        mirror: mirror.to_path_buf(),
+        comparisons,
+    })
+}
+
+fn apply(transform: &Transform2d, mut pose: Pose2d) -> Pose2d {
+    pose.transform(transform);
+    pose
+}
+
+enum Route {
+    Choreo(chor::ChoreoData),
+    Path(PathData, ArcLength),
+}
+
+impl Route {
+    fn load(path: &Path) -> Result<Route> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("traj") => Ok(Route::Choreo(migrate::load::<chor::ChoreoData>(path)?.data)),
+            Some("path") => {
+                let data = migrate::load::<PathData>(path)?.data;
+                let arc = ArcLength::new(&data.anchors());
+                Ok(Route::Path(data, arc))
+            }
+            _ => Err(Error::layout(
+                path,
+                "only .traj and .path files can be compared",
+            )),
+        }
+    }
+
+    fn waypoints(&self) -> Vec<Pose2d> {
+        match self {
+            Route::Choreo(data) => data
+                .params
+                .waypoints
+                .iter()
+                .map(|wp| Pose2d::new(wp.x.val, wp.y.val, wp.heading.val))
+                .collect(),
+            Route::Path(data, arc) => data
+                .anchors()
+                .iter()
+                .enumerate()
+                .map(|(i, a)| pose(a.position, data.rotation_at(arc, i as f64)))
+                .collect(),
+        }
+    }
+
+    // (progress from 0 to 1, pose) along the whole route
+    fn samples(&self) -> Vec<(f64, Pose2d)> {
+        match self {
+            Route::Choreo(data) => {
+                let duration = data.trajectory.duration();
+                data.trajectory
+                    .samples
+                    .iter()
+                    .map(|s| (progress(s.t, duration), s.pose()))
+                    .collect()
+            }
+            Route::Path(data, arc) => arc
+                .sample_evenly(SAMPLE_SPACING_M)
+                .into_iter()
+                .map(|(s, p)| {
+                    let rotation = data.rotation_at(arc, arc.t_at_distance(s));
+                    (progress(s, arc.total_length()), pose(p, rotation))
+                })
+                .collect(),
+        }
+    }
+
+    fn pose_at(&self, progress: f64) -> Option<Pose2d> {
+        match self {
+            Route::Choreo(data) => data
+                .trajectory
+                .sample_at(progress * data.trajectory.duration())
+                .map(|s| s.pose()),
+            Route::Path(data, arc) => {
+                let s = progress * arc.total_length();
+                let t = arc.t_at_distance(s);
+                Some(pose(arc.point_at_distance(s), data.rotation_at(arc, t)))
+            }
+        }
+    }
+}
+
+fn progress(at: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        at / total
+    } else {
+        0.0
+    }
+}
+
+fn pose(position: Vec2d, rotation_degrees: f64) -> Pose2d {
+    Pose2d::new(position.x, position.y, rotation_degrees.to_radians())
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Symmetry::SameAlliance => "same alliance",
+            Symmetry::Alliance => "alliance",
+            Symmetry::Rotational => "rotational",
+        })
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (wp_pos, wp_heading) = self.max_waypoint_error();
+        let (s_pos, s_heading) = self.max_sample_error();
+        write!(
+            f,
+            "{:<13}  waypoints max {:.3} m {:.1}\u{b0}, samples max {:.3} m {:.1}\u{b0}",
+            self.symmetry,
+            wp_pos,
+            wp_heading.to_degrees(),
+            s_pos,
+            s_heading.to_degrees()
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} against {}",
+            self.mirror.display(),
+            self.source.display()
+        )?;
+        for comparison in &self.comparisons {
+            writeln!(f, "  {}", comparison)?;
+        }
+        let best = self.best();
+        writeln!(f, "closest to the {} flip, by waypoint:", best.symmetry)?;
+        for (i, d) in best.waypoints.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:>3}  {:.3} m {:.1}\u{b0}",
+                i + 1,
+                d.position(),
+                d.heading().to_degrees()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::lib::{
+        fixtures,
+        flip::{FIELD_X, FIELD_Y},
+    };
+
+    #[test]
+    fn a_mirror_made_by_hand_matches_its_symmetry() {
+        let deploy = fixtures::project("symmetry");
+        let source = fixtures::write_path(&deploy, "Left", &[[1.0, 1.0], [3.0, 2.0], [5.0, 2.5]]);
+        // the same route on the other alliance's half, facing back across the field
+        let mut json = fixtures::path_json(&[[1.0, 1.0], [3.0, 2.0], [5.0, 2.5]]);
+        for waypoint in json["waypoints"].as_array_mut().unwrap() {
+            for point in ["anchor", "prevControl", "nextControl"] {
+                if let Some(x) = waypoint[point]["x"].as_f64() {
+                    waypoint[point]["x"] = (FIELD_X - x).into();
+                }
+            }
+        }
+        json["idealStartingState"]["rotation"] = 180.0.into();
+        json["goalEndState"]["rotation"] = 180.0.into();
+        let mirror = deploy.join("pathplanner/paths/Right.path");
+        fs::write(&mirror, json.to_string()).unwrap();
+
+        let report = compare(&source, &mirror).unwrap();
+        assert_eq!(report.comparisons.len(), Symmetry::ALL.len());
+        let best = report.best();
+        assert_eq!(best.symmetry, Symmetry::Alliance);
+        let (position, heading) = best.max_sample_error();
+        assert!(position < 1e-6 && heading < 1e-6);
+        assert!(report.comparisons[1].max_position() > 1.0);
+
+        // a route with a waypoint more can't be a mirror of it
+        let longer = fixtures::write_path(
+            &deploy,
+            "Longer",
+            &[[1.0, 1.0], [3.0, 2.0], [5.0, 2.5], [6.0, 3.0]],
+        );
+        assert!(compare(&source, &longer).is_err());
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn rotated_routes_are_reported_as_rotational() {
+        let deploy = fixtures::project("rotational");
+        let source = fixtures::write_path(&deploy, "Left", &[[1.0, 1.0], [4.0, 2.0]]);
+        // turned half way around the field, so facing the other way
+        let anchors = [
+            [FIELD_X - 1.0, FIELD_Y - 1.0],
+            [FIELD_X - 4.0, FIELD_Y - 2.0],
+        ];
+        let mut json = fixtures::path_json(&anchors);
+        json["idealStartingState"]["rotation"] = 180.0.into();
+        json["goalEndState"]["rotation"] = 180.0.into();
+        let mirror = deploy.join("pathplanner/paths/Right.path");
+        fs::write(&mirror, json.to_string()).unwrap();
+
+        let report = compare(&source, &mirror).unwrap();
+        assert_eq!(report.best().symmetry, Symmetry::Rotational);
+        assert!(report.best().max_waypoint_error().0 < 1e-9);
+        let text = report.to_string();
+        assert!(text.contains("closest to the rotational flip, by waypoint:"));
+        assert!(text.contains("    1  0.000 m 0.0\u{b0}"));
+
+        let auto = fixtures::write_auto(&deploy, "Auto", &["Left"]);
+        assert!(compare(&source, &auto).is_err());
+        fixtures::remove(&deploy);
+    }
+}
@@ -1,6 +1,7 @@
 use std::f64::consts::PI;
 
 use crate::lib::{
+    expr,
     flip::{self, Flippable},
     pathplanner::path::PathPoint,
 };
@@ -63,20 +64,55 @@ impl Vec2d {
             self.scale(1.0 / len)
         }
     }
+
+    pub fn cross(self, other: Vec2d) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn dist(self, other: Vec2d) -> f64 {
+        self.sub(other).len()
+    }
+
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pose2d {
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+}
+
+impl Pose2d {
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Self { x, y, heading }
+    }
+
+    pub fn translation(&self) -> Vec2d {
+        Vec2d::new(self.x, self.y)
+    }
 }
 
 impl Flippable for Vec2d {
-    fn flip_alliance(&mut self) {
-        self.x = flip::flip_yaxis(self.x, self.y)[0];
+    fn transform(&mut self, t: &flip::Transform2d) {
+        [self.x, self.y] = t.point([self.x, self.y]);
     }
+}
 
-    fn flip_same_alliance(&mut self) {
-        self.y = flip::flip_xaxis(self.x, self.y)[1];
+impl Flippable for Pose2d {
+    fn transform(&mut self, t: &flip::Transform2d) {
+        [self.x, self.y] = t.point([self.x, self.y]);
+        self.heading = t.heading(self.heading);
     }
 }
 
 pub mod beizer {
-    use crate::lib::{flip::Flippable, util::Vec2d};
+    use crate::lib::{
+        flip::{self, Flippable},
+        util::Vec2d,
+    };
 
     #[derive(Clone)]
     pub struct Anchor {
@@ -86,46 +122,17 @@ pub mod beizer {
     }
 
     impl Flippable for Anchor {
-        fn flip_alliance(&mut self) {
-            self.position.flip_alliance();
-            if self.control_in.is_some() {
-                self.control_in.unwrap().flip_alliance();
-            }
-            if self.control_out.is_some() {
-                self.control_out.unwrap().flip_alliance();
+        fn transform(&mut self, t: &flip::Transform2d) {
+            self.position.transform(t);
+            if let Some(control_in) = self.control_in.as_mut() {
+                control_in.transform(t);
             }
-        }
-
-        fn flip_same_alliance(&mut self) {
-            self.position.flip_same_alliance();
-            if self.control_in.is_some() {
-                self.control_in.unwrap().flip_same_alliance();
-            }
-            if self.control_out.is_some() {
-                self.control_out.unwrap().flip_same_alliance();
+            if let Some(control_out) = self.control_out.as_mut() {
+                control_out.transform(t);
             }
         }
     }
 
-    fn sample_bezier(p0: Vec2d, p1: Vec2d, p2: Vec2d, p3: Vec2d, samples: usize) -> Vec<Vec2d> {
-        let mut result = Vec::with_capacity(samples + 1);
-
-        for i in 0..=samples {
-            let t = i as f64 / samples as f64;
-            let u = 1.0 - t;
-
-            let p = p0
-                .scale(u * u * u)
-                .add(p1.scale(3.0 * u * u * t))
-                .add(p2.scale(3.0 * u * t * t))
-                .add(p3.scale(t * t * t));
-
-            result.push(p);
-        }
-
-        result
-    }
-
     fn beizer_point(p0: Vec2d, p1: Vec2d, p2: Vec2d, p3: Vec2d, t: f64) -> Vec2d {
         let u = 1.0 - t;
         p0.scale(u * u * u)
@@ -134,7 +141,7 @@ pub mod beizer {
             .add(p3.scale(t * t * t))
     }
 
-    pub fn point_at(anchors: &Vec<Anchor>, t: f64) -> Vec2d {
+    pub fn point_at(anchors: &[Anchor], t: f64) -> Vec2d {
         let num_segs = anchors.len() - 1;
         let t_clamped = t.clamp(0.0, num_segs as f64);
         let seg_idx = t_clamped.floor() as usize;
@@ -159,32 +166,457 @@ pub mod beizer {
         )
     }
 
-    pub fn beizer_anchors(anchors: &Vec<Anchor>, samples_per_segment: usize) -> Vec<Vec2d> {
-        assert!(anchors.len() >= 2);
-        let mut traj = Vec::new();
-        for i in 0..anchors.len() - 1 {
-            let a0 = &anchors[i];
-            let a1 = &anchors[i + 1];
-            let p0 = a0.position;
-            let p3 = a1.position;
-            let segment = sample_bezier(
-                p0,
-                a0.control_out.unwrap_or(p0),
-                a1.control_in.unwrap_or(p3),
-                p3,
-                samples_per_segment,
-            );
-            if i > 0 {
-                traj.extend_from_slice(&segment[1..]);
-            } else {
-                traj.extend(segment);
+    fn beizer_derivative(p0: Vec2d, p1: Vec2d, p2: Vec2d, p3: Vec2d, t: f64) -> Vec2d {
+        let u = 1.0 - t;
+        p1.sub(p0)
+            .scale(3.0 * u * u)
+            .add(p2.sub(p1).scale(6.0 * u * t))
+            .add(p3.sub(p2).scale(3.0 * t * t))
+    }
+
+    fn beizer_second_derivative(p0: Vec2d, p1: Vec2d, p2: Vec2d, p3: Vec2d, t: f64) -> Vec2d {
+        let u = 1.0 - t;
+        p2.sub(p1.scale(2.0))
+            .add(p0)
+            .scale(6.0 * u)
+            .add(p3.sub(p2.scale(2.0)).add(p1).scale(6.0 * t))
+    }
+
+    // splits a waypoint relative position into (segment index, local t)
+    fn locate(anchors: &[Anchor], t: f64) -> (usize, f64) {
+        let num_segs = anchors.len() - 1;
+        let t_clamped = t.clamp(0.0, num_segs as f64);
+        let seg_idx = (t_clamped.floor() as usize).min(num_segs - 1);
+        (seg_idx, t_clamped - seg_idx as f64)
+    }
+
+    fn segment_controls(anchors: &[Anchor], seg_idx: usize) -> [Vec2d; 4] {
+        let a0 = &anchors[seg_idx];
+        let a1 = &anchors[seg_idx + 1];
+        let p0 = a0.position;
+        let p3 = a1.position;
+        [
+            p0,
+            a0.control_out.unwrap_or(p0),
+            a1.control_in.unwrap_or(p3),
+            p3,
+        ]
+    }
+
+    pub fn velocity_at(anchors: &[Anchor], t: f64) -> Vec2d {
+        let (seg_idx, t_local) = locate(anchors, t);
+        let [p0, p1, p2, p3] = segment_controls(anchors, seg_idx);
+        let d = beizer_derivative(p0, p1, p2, p3, t_local);
+        if d.len() > 1e-9 {
+            return d;
+        }
+        // degenerate control points (control == anchor) give a zero derivative at the
+        // segment ends, so fall back to the chord direction
+        p3.sub(p0)
+    }
+
+    pub fn tangent_at(anchors: &[Anchor], t: f64) -> Vec2d {
+        velocity_at(anchors, t).norm()
+    }
+
+    pub fn heading_at(anchors: &[Anchor], t: f64) -> f64 {
+        tangent_at(anchors, t).angle()
+    }
+
+    // signed curvature in 1/m, positive when turning counter-clockwise
+    pub fn curvature_at(anchors: &[Anchor], t: f64) -> f64 {
+        let (seg_idx, t_local) = locate(anchors, t);
+        let [p0, p1, p2, p3] = segment_controls(anchors, seg_idx);
+        let d1 = beizer_derivative(p0, p1, p2, p3, t_local);
+        let d2 = beizer_second_derivative(p0, p1, p2, p3, t_local);
+        let speed = d1.len();
+        if speed < 1e-9 {
+            return 0.0;
+        }
+        d1.cross(d2) / (speed * speed * speed)
+    }
+
+    const ARC_STEPS_PER_SEGMENT: usize = 64;
+    // 3 point gauss-legendre nodes and weights on [-1, 1]
+    const GAUSS_NODES: [f64; 3] = [-0.774_596_669_241_483_4, 0.0, 0.774_596_669_241_483_4];
+    const GAUSS_WEIGHTS: [f64; 3] = [5.0 / 9.0, 8.0 / 9.0, 5.0 / 9.0];
+
+    fn arc_length_between(controls: &[Vec2d; 4], t0: f64, t1: f64) -> f64 {
+        let [p0, p1, p2, p3] = *controls;
+        let half = (t1 - t0) / 2.0;
+        let mid = (t1 + t0) / 2.0;
+        GAUSS_NODES
+            .iter()
+            .zip(GAUSS_WEIGHTS.iter())
+            .map(|(n, w)| w * beizer_derivative(p0, p1, p2, p3, mid + half * n).len())
+            .sum::<f64>()
+            * half
+    }
+
+    pub struct NearestPoint {
+        pub t: f64,
+        pub distance_along: f64,
+        pub point: Vec2d,
+        pub offset: f64,
+    }
+
+    // arc length parameterization of a chain of cubic beizer segments, where t is the
+    // waypoint relative position used by pathplanner (segment index + local t)
+    #[derive(Clone)]
+    pub struct ArcLength {
+        anchors: Vec<Anchor>,
+        ts: Vec<f64>,
+        lengths: Vec<f64>,
+    }
+
+    impl ArcLength {
+        pub fn new(anchors: &[Anchor]) -> Self {
+            assert!(anchors.len() >= 2);
+            let num_segs = anchors.len() - 1;
+            let mut ts = vec![0.0];
+            let mut lengths = vec![0.0];
+            for seg_idx in 0..num_segs {
+                let controls = segment_controls(anchors, seg_idx);
+                for step in 0..ARC_STEPS_PER_SEGMENT {
+                    let t0 = step as f64 / ARC_STEPS_PER_SEGMENT as f64;
+                    let t1 = (step + 1) as f64 / ARC_STEPS_PER_SEGMENT as f64;
+                    let s = lengths.last().unwrap() + arc_length_between(&controls, t0, t1);
+                    ts.push(seg_idx as f64 + t1);
+                    lengths.push(s);
+                }
+            }
+            Self {
+                anchors: anchors.to_vec(),
+                ts,
+                lengths,
+            }
+        }
+
+        pub fn anchors(&self) -> &[Anchor] {
+            &self.anchors
+        }
+
+        pub fn total_length(&self) -> f64 {
+            *self.lengths.last().unwrap()
+        }
+
+        pub fn segment_lengths(&self) -> Vec<f64> {
+            self.lengths
+                .iter()
+                .step_by(ARC_STEPS_PER_SEGMENT)
+                .collect::<Vec<_>>()
+                .windows(2)
+                .map(|w| w[1] - w[0])
+                .collect()
+        }
+
+        pub fn distance_at(&self, t: f64) -> f64 {
+            let t = t.clamp(0.0, *self.ts.last().unwrap());
+            let idx = self.ts.partition_point(|tt| *tt < t);
+            if idx == 0 {
+                return 0.0;
+            }
+            let (seg_idx, _) = locate(&self.anchors, self.ts[idx - 1]);
+            let controls = segment_controls(&self.anchors, seg_idx);
+            let base = seg_idx as f64;
+            self.lengths[idx - 1] + arc_length_between(&controls, self.ts[idx - 1] - base, t - base)
+        }
+
+        pub fn t_at_distance(&self, s: f64) -> f64 {
+            let s = s.clamp(0.0, self.total_length());
+            let idx = self.lengths.partition_point(|l| *l < s);
+            if idx == 0 {
+                return 0.0;
+            }
+            let (l0, l1) = (self.lengths[idx - 1], self.lengths[idx]);
+            let (t0, t1) = (self.ts[idx - 1], self.ts[idx]);
+            if l1 - l0 < 1e-12 {
+                return t0;
             }
+            t0 + (t1 - t0) * (s - l0) / (l1 - l0)
         }
 
-        traj
+        pub fn point_at_distance(&self, s: f64) -> Vec2d {
+            point_at(&self.anchors, self.t_at_distance(s))
+        }
+
+        pub fn heading_at_distance(&self, s: f64) -> f64 {
+            heading_at(&self.anchors, self.t_at_distance(s))
+        }
+
+        pub fn curvature_at_distance(&self, s: f64) -> f64 {
+            curvature_at(&self.anchors, self.t_at_distance(s))
+        }
+
+        // evenly spaced (distance, point) pairs, always including both ends
+        pub fn sample_evenly(&self, spacing: f64) -> Vec<(f64, Vec2d)> {
+            let total = self.total_length();
+            let count = (total / spacing.max(1e-3)).ceil().max(1.0) as usize;
+            (0..=count)
+                .map(|i| {
+                    let s = total * i as f64 / count as f64;
+                    (s, self.point_at_distance(s))
+                })
+                .collect()
+        }
+
+        pub fn nearest(&self, p: Vec2d) -> NearestPoint {
+            let mut best_idx = 0;
+            let mut best_dist = f64::INFINITY;
+            for (i, t) in self.ts.iter().enumerate() {
+                let dist = point_at(&self.anchors, *t).dist(p);
+                if dist < best_dist {
+                    best_dist = dist;
+                    best_idx = i;
+                }
+            }
+            // refine between the neighbouring table entries with a ternary search
+            let mut lo = self.ts[best_idx.saturating_sub(1)];
+            let mut hi = self.ts[(best_idx + 1).min(self.ts.len() - 1)];
+            for _ in 0..40 {
+                let m1 = lo + (hi - lo) / 3.0;
+                let m2 = hi - (hi - lo) / 3.0;
+                if point_at(&self.anchors, m1).dist(p) < point_at(&self.anchors, m2).dist(p) {
+                    hi = m2;
+                } else {
+                    lo = m1;
+                }
+            }
+            let t = (lo + hi) / 2.0;
+            let point = point_at(&self.anchors, t);
+            NearestPoint {
+                t,
+                distance_along: self.distance_at(t),
+                point,
+                offset: point.dist(p),
+            }
+        }
     }
 }
 
+// wraps an angle in radians into [-pi, pi)
+pub fn wrap_angle(rad: f64) -> f64 {
+    (rad + PI).rem_euclid(2.0 * PI) - PI
+}
+
+// shortest-path interpolation between two angles in radians, eased with a cosine the
+// same way pathplanner interpolates between rotation targets
+pub fn cosine_interpolate(start: f64, end: f64, t: f64) -> f64 {
+    let eased = (1.0 - (t.clamp(0.0, 1.0) * PI).cos()) / 2.0;
+    start + wrap_angle(end - start) * eased
+}
+
 pub fn deg_to_rad(deg: f64) -> f64 {
     return deg * PI / 180.0;
 }
+
+// rounds to a multiple of step. Goes through a decimal string so the result is the
+// shortest float that prints as the rounded value, and -0.0 becomes 0.0
+pub fn round_to(v: f64, step: f64) -> f64 {
+    if step <= 0.0 || !v.is_finite() {
+        return v;
+    }
+    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+    let rounded: f64 = format!("{:.*}", decimals, (v / step).round() * step)
+        .parse()
+        .unwrap_or(v);
+    rounded + 0.0
+}
+
+// json keys holding angles, in radians for choreo and degrees for pathplanner
+const ANGLE_KEYS: [&str; 4] = ["heading", "rotation", "rotationDegrees", "rotationOffset"];
+
+// json keys holding lengths in meters: positions, and the sizes of choreo's keep in and
+// keep out regions
+const LENGTH_KEYS: [&str; 5] = ["x", "y", "w", "h", "r"];
+
+// rounds every length and angle in a file about to be written. Times, velocities, forces
+// and everything else are left as they are. Choreo {"exp", "val"} pairs whose expression
+// is a plain literal get the literal rewritten to match the rounded value
+pub fn normalize_json(value: &mut serde_json::Value, precision: &flip::Precision, degrees: bool) {
+    normalize_json_in(value, None, precision, degrees);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Quantity {
+    Length,
+    Angle,
+}
+
+impl Quantity {
+    fn of_key(key: &str) -> Option<Quantity> {
+        if LENGTH_KEYS.contains(&key) {
+            Some(Quantity::Length)
+        } else if ANGLE_KEYS.contains(&key) {
+            Some(Quantity::Angle)
+        } else {
+            None
+        }
+    }
+
+    // of a choreo expression variable
+    fn of_dimension(dimension: Option<&str>) -> Option<Quantity> {
+        match dimension {
+            Some("Length") => Some(Quantity::Length),
+            Some("Angle") => Some(Quantity::Angle),
+            _ => None,
+        }
+    }
+
+    fn step(self, precision: &flip::Precision, degrees: bool) -> f64 {
+        match self {
+            Quantity::Length => precision.length,
+            // the same resolution as a decimal step, 1e-9 rad -> 1e-8 deg
+            Quantity::Angle if degrees => 10f64.powf(precision.angle.to_degrees().log10().floor()),
+            Quantity::Angle => precision.angle,
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            Quantity::Length => "m",
+            Quantity::Angle => "rad",
+        }
+    }
+}
+
+// `quantity` is what `value` holds, when it's rounded at all
+fn normalize_json_in(
+    value: &mut serde_json::Value,
+    quantity: Option<Quantity>,
+    precision: &flip::Precision,
+    degrees: bool,
+) {
+    use serde_json::Value;
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let Some(quantity) = quantity else {
+                return;
+            };
+            let v = round_to(
+                n.as_f64().unwrap_or_default(),
+                quantity.step(precision, degrees),
+            );
+            if let Some(n) = serde_json::Number::from_f64(v) {
+                *value = Value::Number(n);
+            }
+        }
+        Value::Array(arr) => arr
+            .iter_mut()
+            .for_each(|v| normalize_json_in(v, quantity, precision, degrees)),
+        Value::Object(map) if map.len() == 2 && map.contains_key("exp") => {
+            let Some(quantity) = quantity else {
+                return;
+            };
+            let exp = map.get("exp").and_then(Value::as_str).unwrap_or_default();
+            let parsed = expr::Expr::parse(exp);
+            // choreo works the value out from the variables itself, so it's left as it is
+            if parsed.as_ref().is_ok_and(|e| e.has_vars()) {
+                return;
+            }
+            if let Some(val) = map.get_mut("val") {
+                normalize_json_in(val, Some(quantity), precision, degrees);
+            }
+            let val = map.get("val").and_then(Value::as_f64).unwrap_or_default();
+            // and a literal is written again when it no longer says what the value is
+            if let Ok(e) = parsed {
+                if e.eval(&Default::default()).is_ok_and(|v| v != val) {
+                    let unit = e.literal_unit().unwrap_or(quantity.unit());
+                    let factor = expr::unit_factor(unit).unwrap_or(1.0);
+                    map.insert(
+                        "exp".to_owned(),
+                        Value::String(expr::Expr::literal(val / factor, unit).to_string()),
+                    );
+                }
+            }
+        }
+        Value::Object(map) => {
+            let dimension = Quantity::of_dimension(map.get("dimension").and_then(Value::as_str));
+            for (k, v) in map.iter_mut() {
+                let quantity = match k.as_str() {
+                    "var" => dimension,
+                    k => Quantity::of_key(k),
+                };
+                normalize_json_in(v, quantity, precision, degrees);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arc_length_inverts_distance() {
+        use beizer::{Anchor, ArcLength};
+        let anchor = |x, y, control_in: Option<[f64; 2]>, control_out: Option<[f64; 2]>| Anchor {
+            position: Vec2d::new(x, y),
+            control_in: control_in.map(Vec2d::from_array),
+            control_out: control_out.map(Vec2d::from_array),
+        };
+        // 3 m along x with controls bunched at the start, so t and distance don't agree,
+        // then 4 m up
+        let arc = ArcLength::new(&[
+            anchor(0.0, 0.0, None, Some([0.5, 0.0])),
+            anchor(3.0, 0.0, Some([2.0, 0.0]), Some([3.0, 1.0])),
+            anchor(3.0, 4.0, Some([3.0, 3.0]), None),
+        ]);
+        assert!((arc.total_length() - 7.0).abs() < 1e-9);
+        let lengths = arc.segment_lengths();
+        assert!((lengths[0] - 3.0).abs() < 1e-9 && (lengths[1] - 4.0).abs() < 1e-9);
+        for s in [0.0, 0.7, 2.9, 3.0, 5.5, 7.0] {
+            let along = if s <= 3.0 {
+                Vec2d::new(s, 0.0)
+            } else {
+                Vec2d::new(3.0, s - 3.0)
+            };
+            assert!(
+                (arc.distance_at(arc.t_at_distance(s)) - s).abs() < 1e-3,
+                "at {}",
+                s
+            );
+            assert!(arc.point_at_distance(s).dist(along) < 1e-3, "at {}", s);
+        }
+        assert!(arc.curvature_at_distance(1.0).abs() < 1e-9);
+
+        let nearest = arc.nearest(Vec2d::new(1.5, 1.0));
+        assert!(nearest.point.dist(Vec2d::new(1.5, 0.0)) < 1e-6);
+        assert!((nearest.offset - 1.0).abs() < 1e-6);
+        assert!((nearest.distance_along - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn only_lengths_and_angles_are_rounded() {
+        let precision = flip::Precision {
+            length: 0.01,
+            angle: 0.001,
+        };
+        let mut json = serde_json::json!({
+            "samples": [{ "t": 0.123456, "x": 1.23456, "heading": 0.123456, "vx": 1.23456 }],
+            // flipped, so the literal no longer matches
+            "waypoint": { "x": { "exp": "2 m", "val": 1.23456 } },
+            "variableWaypoint": { "x": { "exp": "ReefX + 0.123456 m", "val": 4.623456 } },
+            "waypointRelativePos": 0.123456,
+            "variables": {
+                "Gap": { "dimension": "Length", "var": { "exp": "0.3049 m", "val": 0.3049 } },
+                "Speed": { "dimension": "LinVel", "var": { "exp": "1.2345 m / s", "val": 1.2345 } }
+            }
+        });
+        normalize_json(&mut json, &precision, false);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "samples": [{ "t": 0.123456, "x": 1.23, "heading": 0.123, "vx": 1.23456 }],
+                "waypoint": { "x": { "exp": "1.23 m", "val": 1.23 } },
+                "variableWaypoint": { "x": { "exp": "ReefX + 0.123456 m", "val": 4.623456 } },
+                "waypointRelativePos": 0.123456,
+                "variables": {
+                    "Gap": { "dimension": "Length", "var": { "exp": "0.3 m", "val": 0.3 } },
+                    "Speed": { "dimension": "LinVel", "var": { "exp": "1.2345 m / s", "val": 1.2345 } }
+                }
+            })
+        );
+    }
+}
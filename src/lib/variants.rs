@@ -0,0 +1,266 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::lib::{
+    error::{Error, Result},
+    flip::{FlipOptions, TransformStep},
+    manifest, migrate,
+    pathplanner::auto::AutoData,
+    plan::WritePlan,
+    project,
+};
+
+// several flips of one auto, path or trajectory made in one go, such as the other side of
+// the field for both alliances. The original is the source itself and is left as it is.
+// Each variant names its outputs by its own rule, in which "{name}" stands for the name of
+// the file flipped, so an auto's paths get names of their own
+
+pub const NAME_PLACEHOLDER: &str = "{name}";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Variant {
+    SameAlliance,
+    Alliance,
+    // the same alliance flip followed by the alliance flip
+    Both,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [Variant::SameAlliance, Variant::Alliance, Variant::Both];
+
+    pub fn default_rule(self) -> &'static str {
+        match self {
+            Variant::SameAlliance => "{name}_Mirrored",
+            Variant::Alliance => "{name}_OtherAlliance",
+            Variant::Both => "{name}_OtherAllianceMirrored",
+        }
+    }
+
+    // `base` flipped this way instead of however it was set to
+    pub fn options(self, base: &FlipOptions) -> FlipOptions {
+        let mut opts = FlipOptions {
+            same_alliance: self == Variant::SameAlliance,
+            custom_transform: Vec::new(),
+            in_place: false,
+            ..base.clone()
+        };
+        if self == Variant::Both {
+            // about the center of the field the others flip on
+            let [x, y] = base.field().center();
+            opts.custom_transform = vec![
+                TransformStep::Reflect { degrees: 0.0, x, y },
+                TransformStep::Reflect {
+                    degrees: 90.0,
+                    x,
+                    y,
+                },
+            ];
+        }
+        opts
+    }
+}
+
+// what one variant writes: the flipped file and, for an auto, each of its paths with
+// where its flip goes
+pub struct VariantFlip {
+    pub variant: Variant,
+    pub output: PathBuf,
+    pub paths: Vec<(PathBuf, PathBuf)>,
+}
+
+// the name `rule` gives the variant of a file named `name`
+pub fn apply_rule(rule: &str, name: &str) -> String {
+    rule.replace(NAME_PLACEHOLDER, name)
+}
+
+// the paths or trajectories `source` follows when it's an auto, none otherwise
+pub fn auto_paths(source: &Path) -> Result<Vec<PathBuf>> {
+    if source.extension().is_none_or(|e| e != "auto") {
+        return Ok(Vec::new());
+    }
+    let data = migrate::load::<AutoData>(source)?.data;
+    let (names, is_chor) = data.get_filenames();
+    project::Layout::discover(source).auto_paths(source, &names, is_chor)
+}
+
+// where each variant of `source` and of the auto's `paths` goes: in `output_dir`, or next
+// to the file flipped when that's None. No two outputs may share a file, and none may be
+// one of the sources
+pub fn outputs(
+    source: &Path,
+    paths: &[PathBuf],
+    rules: &[(Variant, String)],
+    output_dir: Option<&Path>,
+) -> Result<Vec<VariantFlip>> {
+    let mut taken: Vec<PathBuf> = std::iter::once(source)
+        .chain(paths.iter().map(PathBuf::as_path))
+        .map(project::absolute)
+        .collect();
+    let mut flips = Vec::new();
+    for (variant, rule) in rules {
+        let output = named(source, rule, output_dir, &mut taken)?;
+        let paths = paths
+            .iter()
+            .map(|path| Ok((path.clone(), named(path, rule, output_dir, &mut taken)?)))
+            .collect::<Result<_>>()?;
+        flips.push(VariantFlip {
+            variant: *variant,
+            output,
+            paths,
+        });
+    }
+    Ok(flips)
+}
+
+fn named(
+    file: &Path,
+    rule: &str,
+    output_dir: Option<&Path>,
+    taken: &mut Vec<PathBuf>,
+) -> Result<PathBuf> {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let name = apply_rule(rule, &stem);
+    // dots would read as a choreo split in an auto
+    if name.is_empty() || name.contains(['.', '/', '\\', '\0']) {
+        return Err(Error::layout(
+            file,
+            format!(
+                "the naming rule \"{}\" gives \"{}\", which isn't a file name",
+                rule, name
+            ),
+        ));
+    }
+    let ext = file.extension().unwrap_or_default().to_string_lossy();
+    let dir = output_dir.or(file.parent()).unwrap_or(Path::new(""));
+    let output = dir.join(format!("{}.{}", name, ext));
+    let absolute = project::absolute(&output);
+    if taken.contains(&absolute) {
+        return Err(Error::layout(
+            &output,
+            "is either a source or named by another variant, give each variant a rule with \
+             {name} in it that the others don't have",
+        ));
+    }
+    taken.push(absolute);
+    Ok(output)
+}
+
+// plans and records every variant in the same plan, so all of them are confirmed and
+// written together
+pub fn plan_variants(
+    source: &Path,
+    flips: &[VariantFlip],
+    base: &FlipOptions,
+    plan: &mut WritePlan,
+) -> Result<()> {
+    // combined flips are made about the center of the venue the project is calibrated to
+    let base = FlipOptions {
+        calibration: manifest::calibration(source)?,
+        ..base.clone()
+    };
+    for flip in flips {
+        let opts = flip.variant.options(&base);
+        manifest::plan_flip(source, &flip.output, &flip.paths, &opts, plan)?;
+        manifest::record_flip(source, &flip.output, &flip.paths, &opts, plan)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Variant::SameAlliance => "same alliance",
+            Variant::Alliance => "alliance",
+            Variant::Both => "both",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{
+        fixtures,
+        flip::{Field, Flippable, Transform2d},
+        manifest::{Manifest, MANIFEST_NAME},
+        util::{self, Pose2d},
+    };
+
+    fn defaults() -> Vec<(Variant, String)> {
+        Variant::ALL
+            .into_iter()
+            .map(|v| (v, v.default_rule().to_owned()))
+            .collect()
+    }
+
+    fn names(flip: &VariantFlip) -> Vec<String> {
+        std::iter::once(&flip.output)
+            .chain(flip.paths.iter().map(|(_, output)| output))
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn each_variant_names_the_auto_and_its_paths() {
+        let deploy = fixtures::project("variants");
+        fixtures::write_path(&deploy, "B", &[[2.0, 1.0], [4.0, 1.0]]);
+        fixtures::write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        let auto = fixtures::write_auto(&deploy, "Auto", &["B", "A"]);
+        let paths = auto_paths(&auto).unwrap();
+
+        let flips = outputs(&auto, &paths, &defaults(), None).unwrap();
+        assert_eq!(
+            flips.iter().map(names).collect::<Vec<_>>(),
+            vec![
+                vec!["Auto_Mirrored.auto", "A_Mirrored.path", "B_Mirrored.path"],
+                vec![
+                    "Auto_OtherAlliance.auto",
+                    "A_OtherAlliance.path",
+                    "B_OtherAlliance.path"
+                ],
+                vec![
+                    "Auto_OtherAllianceMirrored.auto",
+                    "A_OtherAllianceMirrored.path",
+                    "B_OtherAllianceMirrored.path"
+                ],
+            ]
+        );
+        // outputs go next to what they flip unless a folder is given
+        assert_eq!(flips[0].paths[0].1.parent(), paths[0].parent());
+        let elsewhere = deploy.join("flipped");
+        let flips = outputs(&auto, &paths, &defaults(), Some(&elsewhere)).unwrap();
+        assert_eq!(flips[0].output, elsewhere.join("Auto_Mirrored.auto"));
+
+        // without {name} the paths would share a file, and as it is they'd be the sources
+        for rule in ["Flipped", "{name}", "{name}.1"] {
+            let rules = [(Variant::Alliance, rule.to_owned())];
+            assert!(outputs(&auto, &paths, &rules, None).is_err(), "{}", rule);
+        }
+        fixtures::remove(&deploy);
+    }
+
+    #[test]
+    fn every_variant_is_planned_and_recorded() {
+        let deploy = fixtures::project("variant-plan");
+        let source = fixtures::write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        let flips = outputs(&source, &[], &defaults(), None).unwrap();
+        let mut plan = WritePlan::default();
+        plan_variants(&source, &flips, &FlipOptions::default(), &mut plan).unwrap();
+        plan.commit().unwrap();
+
+        assert!(flips.iter().all(|f| f.output.is_file()));
+        let manifest = Manifest::load(&deploy.join(MANIFEST_NAME), &WritePlan::default()).unwrap();
+        assert_eq!(manifest.entries.len(), Variant::ALL.len());
+
+        // both flips together turn the field half way around
+        let both = Variant::Both.options(&FlipOptions::default()).transform();
+        let (mut flipped, mut rotated) = (Pose2d::new(1.0, 2.0, 0.5), Pose2d::new(1.0, 2.0, 0.5));
+        flipped.transform(&both);
+        rotated.transform(&Transform2d::rotational(&Field::NOMINAL));
+        assert!(flipped.translation().dist(rotated.translation()) < 1e-9);
+        assert!(util::wrap_angle(flipped.heading - rotated.heading).abs() < 1e-9);
+        fixtures::remove(&deploy);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::lib::{
+    error::Result,
+    manifest::{self, Manifest},
+    plan::WritePlan,
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// polls the modification times of the sources a manifest tracks. A change is only reported
+// once the file has stopped changing for a poll, so a save in progress isn't read half
+// written
+pub struct Watcher {
+    pub manifest: PathBuf,
+    seen: HashMap<PathBuf, Option<SystemTime>>,
+    reported: HashMap<PathBuf, Option<SystemTime>>,
+    last_poll: Option<Instant>,
+}
+
+impl Watcher {
+    pub fn new(manifest: PathBuf) -> Result<Self> {
+        manifest::check_exists(&manifest)?;
+        let mut watcher = Self {
+            manifest,
+            seen: HashMap::new(),
+            reported: HashMap::new(),
+            last_poll: None,
+        };
+        // changes from before the watch started are left to a regenerate
+        let _ = watcher.poll();
+        watcher.reported = watcher.seen.clone();
+        Ok(watcher)
+    }
+
+    // the tracked sources that changed since they were last reported, or nothing when the
+    // last poll was less than POLL_INTERVAL ago
+    pub fn poll(&mut self) -> Result<Vec<PathBuf>> {
+        if self
+            .last_poll
+            .is_some_and(|last| last.elapsed() < POLL_INTERVAL)
+        {
+            return Ok(Vec::new());
+        }
+        self.last_poll = Some(Instant::now());
+        let root = self.manifest.parent().unwrap_or(Path::new(""));
+        let sources = Manifest::load(&self.manifest, &WritePlan::default())?.sources(root);
+        let mut changed = Vec::new();
+        for source in sources {
+            let stamp = fs::metadata(&source).and_then(|m| m.modified()).ok();
+            let seen = self.seen.insert(source.clone(), stamp);
+            if seen != Some(stamp) {
+                // still being written, or new to the manifest
+                continue;
+            }
+            if self.reported.get(&source) != Some(&stamp) {
+                self.reported.insert(source.clone(), stamp);
+                changed.push(source);
+            }
+        }
+        Ok(changed)
+    }
+
+    // regenerates and writes the outputs of changed sources, returning the outputs
+    // rebuilt, or None when no source changed. Watching is consent to overwrite the
+    // outputs the manifest lists
+    pub fn sync(&mut self) -> Result<Option<Vec<PathBuf>>> {
+        if self.poll()?.is_empty() {
+            return Ok(None);
+        }
+        let mut plan = WritePlan::default();
+        let rebuilt = manifest::regenerate(&self.manifest, &mut plan)?;
+        plan.commit()?;
+        Ok(Some(rebuilt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{fixtures, flip::FlipOptions};
+
+    // saves `file` as last modified `secs` after the epoch
+    fn touch(file: &Path, secs: u64) {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        fs::File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn sources_are_reported_once_they_stop_changing() {
+        let deploy = fixtures::project("watch");
+        let source = fixtures::write_path(&deploy, "A", &[[1.0, 1.0], [3.0, 1.0]]);
+        let output = source.with_file_name("A_Mirrored.path");
+        let mut plan = WritePlan::default();
+        let opts = FlipOptions::default();
+        manifest::plan_flip(&source, &output, &[], &opts, &mut plan).unwrap();
+        manifest::record_flip(&source, &output, &[], &opts, &mut plan).unwrap();
+        plan.commit().unwrap();
+
+        let mut watcher = Watcher::new(deploy.join(manifest::MANIFEST_NAME)).unwrap();
+        let poll = |watcher: &mut Watcher| {
+            watcher.last_poll = None;
+            watcher.poll().unwrap()
+        };
+        assert!(poll(&mut watcher).is_empty());
+        touch(&source, 1_000);
+        // seen changing, then the same on the next poll
+        assert!(poll(&mut watcher).is_empty());
+        assert_eq!(poll(&mut watcher), vec![source.clone()]);
+        assert!(poll(&mut watcher).is_empty());
+
+        // polls closer together than the interval don't look
+        touch(&source, 2_000);
+        assert!(watcher.poll().unwrap().is_empty());
+        assert!(poll(&mut watcher).is_empty());
+        fixtures::write_path(&deploy, "A", &[[1.5, 1.0], [3.5, 1.0]]);
+        touch(&source, 3_000);
+        assert!(poll(&mut watcher).is_empty());
+        watcher.last_poll = None;
+        assert_eq!(watcher.sync().unwrap(), Some(vec![output]));
+        fixtures::remove(&deploy);
+    }
+}
@@ -1,30 +1,59 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use eframe::egui::{self, DroppedFile, Frame, RichText};
+use lib::calibration;
+use lib::check;
 use lib::chor;
+use lib::error;
 use lib::flip;
+use lib::manifest;
+use lib::migrate;
 use lib::pathplanner;
+use lib::plan;
+use lib::project;
+use lib::symmetry;
+use lib::variants;
+use lib::watch;
 use std::ffi::OsStr;
 use std::{
-    fs::File,
-    io::{Result},
+    io::Result,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 use walkdir::{DirEntry, WalkDir};
 
-use crate::lib::{
-    plot::{self, Plotter},
-};
+use crate::lib::plot::{self, Plotter};
 
+mod cli;
 mod lib {
+    pub mod calibration;
+    pub mod check;
     pub mod chor;
+    pub mod error;
+    pub mod expr;
+    #[cfg(test)]
+    pub mod fixtures;
     pub mod flip;
+    pub mod format;
+    pub mod manifest;
+    pub mod migrate;
     pub mod pathplanner;
+    pub mod plan;
     pub mod plot;
+    pub mod project;
+    pub mod symmetry;
     pub mod util;
+    pub mod variants;
+    pub mod watch;
 }
 
-fn main() -> eframe::Result {
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        #[cfg(all(windows, not(debug_assertions)))]
+        attach_console();
+        return cli::run(&args);
+    }
     let opts = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_active(true)
@@ -32,11 +61,34 @@ fn main() -> eframe::Result {
             .with_drag_and_drop(true),
         ..Default::default()
     };
-    eframe::run_native(
+    let result = eframe::run_native(
         "Choreo Path Flipper",
         opts,
         Box::new(|_| Ok(Box::new(PathFlip::new()))),
-    )
+    );
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// release builds are windows gui programs, which start without a console, so the command
+// line prints to the one it was run from. Output redirected to a file already has its
+// handles and is left alone
+#[cfg(all(windows, not(debug_assertions)))]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // fails when it wasn't run from a console, where there's nothing to print to anyway
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -55,39 +107,14 @@ impl FlipFileType {
         };
     }
 
-    pub fn check_file(&self, f: &File) -> bool {
+    // loads the file through migrate so older schemas are accepted and unsupported
+    // versions explain themselves
+    pub fn check_file(&self, path: &Path) -> error::Result<()> {
         match self {
-            Self::Choreo => {
-                let parsed: serde_json::Result<chor::ChoreoData> = serde_json::from_reader(f);
-                match parsed {
-                    Ok(_) => true,
-                    Err(err) => {
-                        println!("{}", err);
-                        false
-                    }
-                }
-            }
-            Self::Pathplanner => {
-                let parsed: serde_json::Result<pathplanner::path::PathData> =
-                    serde_json::from_reader(f);
-                match parsed {
-                    Ok(_) => true,
-                    Err(err) => {
-                        println!("{}", err);
-                        false
-                    }
-                }
-            }
+            Self::Choreo => migrate::load::<chor::ChoreoData>(path).map(|_| ()),
+            Self::Pathplanner => migrate::load::<pathplanner::path::PathData>(path).map(|_| ()),
             Self::PathplannerAuto { is_chor: _ } => {
-                let parsed: serde_json::Result<pathplanner::auto::AutoData> =
-                    serde_json::from_reader(f);
-                match parsed {
-                    Ok(_) => true,
-                    Err(err) => {
-                        println!("{}", err);
-                        false
-                    }
-                }
+                migrate::load::<pathplanner::auto::AutoData>(path).map(|_| ())
             }
         }
     }
@@ -97,10 +124,27 @@ struct PathFlip {
     dropped_files: Vec<egui::DroppedFile>,
     auto_files: Vec<PathBuf>,
     auto_file_names: Vec<String>,
-    auto_file_prefs: Vec<String>,
     auto_file_valids: Vec<bool>,
     picked_path: Option<String>,
     flip_same_alliance: bool,
+    mirror_headings: bool,
+    // used instead of the flip when there are any
+    transform_steps: Vec<flip::TransformStep>,
+    // several flips made at once instead of one: each variant, whether it's made and the
+    // rule naming its outputs
+    make_variants: bool,
+    variants: Vec<(variants::Variant, bool, String)>,
+    time_scale: f64,
+    resample: bool,
+    resample_dt: f64,
+    mirror_variables: bool,
+    round_length_digits: i32,
+    round_angle_digits: i32,
+    write_latest: bool,
+    reverse: bool,
+    // reversed without being flipped
+    reverse_in_place: bool,
+    flip_anyway: bool,
     outputname: String,
     recalc_path: bool,
     outputname_valid: bool,
@@ -114,9 +158,17 @@ struct PathFlip {
     units_is_imp: bool,
     modal_open: bool,
     use_curr_dir: bool,
+    output_dir: Option<PathBuf>,
     write_status: String,
-    write_err: bool,
-    dir_prefx: String,
+    errors: Vec<error::Error>,
+    // a flip waiting for its writes to be confirmed
+    pending_plan: Option<plan::WritePlan>,
+    allow_overwrite: bool,
+    watcher: Option<watch::Watcher>,
+    // the open file against a mirror of it picked to compare with
+    symmetry: Option<symmetry::Report>,
+    // the venue the open file's project is calibrated to
+    calibration: Option<calibration::FieldProfile>,
     chassis_color: [u8; 3],
 }
 
@@ -126,10 +178,26 @@ impl Default for PathFlip {
             dropped_files: Default::default(),
             auto_files: Vec::new(),
             auto_file_names: Vec::new(),
-            auto_file_prefs: Vec::new(),
             auto_file_valids: Vec::new(),
             picked_path: Default::default(),
             flip_same_alliance: true,
+            mirror_headings: false,
+            transform_steps: Vec::new(),
+            make_variants: false,
+            variants: variants::Variant::ALL
+                .into_iter()
+                .map(|v| (v, true, v.default_rule().to_owned()))
+                .collect(),
+            time_scale: 1.0,
+            resample: false,
+            resample_dt: 0.05,
+            mirror_variables: true,
+            round_length_digits: 9,
+            round_angle_digits: 9,
+            write_latest: false,
+            reverse: false,
+            reverse_in_place: false,
+            flip_anyway: false,
             outputname: Default::default(),
             path_type: FlipFileType::Choreo,
             recalc_path: false,
@@ -143,9 +211,14 @@ impl Default for PathFlip {
             units_is_imp: false,
             outputname_valid: false,
             use_curr_dir: true,
-            dir_prefx: "C:\\".to_string(),
+            output_dir: None,
             write_status: Default::default(),
-            write_err: false,
+            errors: Vec::new(),
+            pending_plan: None,
+            symmetry: None,
+            calibration: None,
+            allow_overwrite: false,
+            watcher: None,
             chassis_color: egui::Color32::PURPLE
                 .to_array()
                 .split_last()
@@ -162,65 +235,369 @@ impl PathFlip {
         Default::default()
     }
 
-    pub fn load_file(&mut self, path: &PathBuf) {
-        let path_str = path.display().to_string();
-        self.picked_path = Some(path_str);
+    pub fn load_file(&mut self, path: &Path) {
+        self.picked_path = Some(path.display().to_string());
         self.path_is_valid_file = false;
-        if let Some(picked_path) = &self.picked_path {
-            let ext = path.extension().unwrap_or(OsStr::new(""));
-            if ext
-                == (FlipFileType::PathplannerAuto { is_chor: false })
-                    .get_ext()
-                    .as_str()
-            {
-                self.picked_path = Some(path.display().to_string());
-                self.path_is_valid_file = false;
-                if let Some(parent) = path.parent() {
-                    if let Ok(file) = File::open(&path) {
-                        if let Ok(data) =
-                            serde_json::from_reader::<&File, pathplanner::auto::AutoData>(&file)
-                        {
-                            let names = data.get_filenames();
-                            let path = if names.1 {
-                                parent
-                                    .parent()
-                                    .unwrap() // autos -> pathplanner
-                                    .parent()
-                                    .unwrap() // pathplanner -> deploy
-                                    .join("choreo")
-                            } else {
-                                parent
-                                    .parent()
-                                    .unwrap() // autos -> pathplanner
-                                    .join("paths")
-                            };
-                            self.auto_files.clear();
-                            self.auto_files
-                                .extend(names.0.iter().map(|s| PathBuf::from(path.join(s))));
-                            self.auto_file_prefs =
-                                self.auto_files.iter().map(|_| String::new()).collect();
-                            self.auto_file_names =
-                                self.auto_files.iter().map(|_| String::new()).collect();
-                            self.auto_file_valids = self.auto_files.iter().map(|_| false).collect();
-                            self.path_type = FlipFileType::PathplannerAuto { is_chor: names.1 };
-                            self.path_is_valid_file = true;
-                            self.recalc_path = true;
-                        }
+        self.flip_anyway = false;
+        self.pending_plan = None;
+        self.symmetry = None;
+        self.write_status.clear();
+        self.errors.clear();
+        if let Err(err) = self.try_load_file(path) {
+            self.errors.push(err);
+        }
+        self.refresh_calibration();
+    }
+
+    fn refresh_calibration(&mut self) {
+        self.calibration = None;
+        if let Some(path) = &self.picked_path {
+            match manifest::calibration(Path::new(path)) {
+                Ok(profile) => self.calibration = profile,
+                Err(err) => self.errors.push(err),
+            }
+        }
+    }
+
+    // the folder flipped files for `input` go in: next to it, or the picked folder
+    fn output_dir_for(&self, input: &Path) -> Option<PathBuf> {
+        if self.use_curr_dir {
+            input.parent().map(Path::to_path_buf)
+        } else {
+            self.output_dir.clone()
+        }
+    }
+
+    // the auto's paths and where each flipped one goes, in the order they're listed
+    fn auto_pairs(&self) -> Option<Vec<(PathBuf, PathBuf)>> {
+        let ext = match self.path_type {
+            FlipFileType::PathplannerAuto { is_chor: true } => "traj",
+            _ => "path",
+        };
+        self.auto_files
+            .iter()
+            .zip(&self.auto_file_names)
+            .map(|(path, name)| {
+                let output = self.output_dir_for(path)?.join(format!("{}.{}", name, ext));
+                Some((path.clone(), output))
+            })
+            .collect()
+    }
+
+    // the manifest of the open file's project, or of a folder picked for it
+    fn project_manifest(&self) -> Option<PathBuf> {
+        let start = match &self.picked_path {
+            Some(path) => PathBuf::from(path),
+            None => rfd::FileDialog::new()
+                .pick_folder()?
+                .join(manifest::MANIFEST_NAME),
+        };
+        Some(manifest::manifest_path(&start))
+    }
+
+    fn regenerate(&mut self, manifest: &Path) {
+        let mut plan = plan::WritePlan::default();
+        self.write_status.clear();
+        match manifest::regenerate(manifest, &mut plan) {
+            Ok(rebuilt) if rebuilt.is_empty() => {
+                self.write_status = "Every flipped file is up to date".to_owned()
+            }
+            Ok(_) => {
+                self.pending_plan = Some(plan);
+                self.allow_overwrite = false;
+            }
+            Err(err) => self.errors.push(err),
+        }
+    }
+
+    // moves the project onto `profile`'s field, or back to the nominal one
+    fn calibrate(&mut self, manifest: &Path, profile: Option<calibration::FieldProfile>) {
+        let mut plan = plan::WritePlan::default();
+        self.write_status.clear();
+        let precision = self.flip_options().precision;
+        match calibration::calibrate(manifest, profile, &precision, &mut plan) {
+            Ok(_) if plan.writes.is_empty() => {
+                self.write_status = "The project is on that field already".to_owned()
+            }
+            Ok(_) => {
+                self.pending_plan = Some(plan);
+                self.allow_overwrite = false;
+            }
+            Err(err) => self.errors.push(err),
+        }
+    }
+
+    fn check(&mut self, manifest: &Path) {
+        self.write_status.clear();
+        match check::check(manifest, check::DEFAULT_TOLERANCE) {
+            Ok(findings) if findings.is_empty() => {
+                self.write_status = "Every flipped file matches its source".to_owned()
+            }
+            Ok(findings) => {
+                self.write_status = findings
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            Err(err) => self.errors.push(err),
+        }
+    }
+
+    fn flip_options(&self) -> flip::FlipOptions {
+        let reverse =
+            self.reverse && !matches!(self.path_type, FlipFileType::PathplannerAuto { .. });
+        flip::FlipOptions {
+            same_alliance: self.flip_same_alliance,
+            mirror_headings: self.mirror_headings,
+            custom_transform: self.transform_steps.clone(),
+            in_place: reverse && self.reverse_in_place,
+            time_scale: self.time_scale,
+            resample_dt: self.resample.then_some(self.resample_dt),
+            mirror_variables: self.mirror_variables,
+            precision: flip::Precision {
+                length: 10f64.powi(-self.round_length_digits),
+                angle: 10f64.powi(-self.round_angle_digits),
+            },
+            write_latest: self.write_latest,
+            reverse,
+            calibration: self.calibration.clone(),
+        }
+    }
+
+    // the steps of a custom transform, applied top to bottom. Returns whether they changed
+    fn transform_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        use flip::TransformStep;
+        let mut changed = false;
+        let mut remove = None;
+        for (i, step) in self.transform_steps.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let (name, fields) = match step {
+                    TransformStep::Translate { x, y } => {
+                        ("Translate", vec![("x", x, " m"), ("y", y, " m")])
                     }
+                    TransformStep::Rotate { degrees, x, y } => (
+                        "Rotate",
+                        vec![
+                            ("by", degrees, "\u{b0}"),
+                            ("about x", x, " m"),
+                            ("y", y, " m"),
+                        ],
+                    ),
+                    TransformStep::Reflect { degrees, x, y } => (
+                        "Reflect",
+                        vec![
+                            ("across the line at", degrees, "\u{b0}"),
+                            ("through x", x, " m"),
+                            ("y", y, " m"),
+                        ],
+                    ),
+                };
+                ui.label(format!("{}. {}", i + 1, name));
+                for (label, value, suffix) in fields {
+                    ui.label(label);
+                    changed |= ui
+                        .add(egui::DragValue::new(value).speed(0.01).suffix(suffix))
+                        .changed();
                 }
-            } else {
-                if let Ok(file) = File::open(picked_path) {
-                    for i in [FlipFileType::Choreo, FlipFileType::Pathplanner] {
-                        if i.get_ext().as_str() == ext && i.check_file(&file) {
-                            self.path_is_valid_file = true;
-                            self.recalc_path = true;
-                            self.path_type = i;
-                            self.auto_files.clear();
-                        }
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.transform_steps.remove(i);
+            changed = true;
+        }
+        // about the center of the venue the project is calibrated to
+        let [cx, cy] = self.flip_options().field().center();
+        ui.horizontal(|ui| {
+            ui.label("Add a step:");
+            for (name, step) in [
+                ("Translate", TransformStep::Translate { x: 0.0, y: 0.0 }),
+                (
+                    "Rotate",
+                    TransformStep::Rotate {
+                        degrees: 180.0,
+                        x: cx,
+                        y: cy,
+                    },
+                ),
+                (
+                    "Reflect",
+                    TransformStep::Reflect {
+                        degrees: 90.0,
+                        x: cx,
+                        y: cy,
+                    },
+                ),
+            ] {
+                if ui.button(name).clicked() {
+                    self.transform_steps.push(step);
+                    changed = true;
+                }
+            }
+        });
+        changed
+    }
+
+    fn variant_rules(&self) -> Vec<(variants::Variant, String)> {
+        self.variants
+            .iter()
+            .filter(|(_, on, _)| *on)
+            .map(|(v, _, rule)| (*v, rule.clone()))
+            .collect()
+    }
+
+    // the files every selected variant of `picked` writes, None without an output folder
+    fn variant_outputs(&self, picked: &Path) -> Option<error::Result<Vec<variants::VariantFlip>>> {
+        let dir = if self.use_curr_dir {
+            None
+        } else {
+            Some(self.output_dir.as_deref()?)
+        };
+        Some(variants::outputs(
+            picked,
+            &self.auto_files,
+            &self.variant_rules(),
+            dir,
+        ))
+    }
+
+    // which variants to make and how each is named, with the files they write. Returns
+    // whether the selection changed
+    fn variants_ui(&mut self, ui: &mut egui::Ui, picked: &Path) -> bool {
+        let mut changed = false;
+        for (variant, on, rule) in &mut self.variants {
+            ui.horizontal(|ui| {
+                changed |= ui.checkbox(on, variant.to_string()).changed();
+                ui.label("named");
+                ui.add_enabled(*on, egui::TextEdit::singleline(rule));
+            });
+        }
+        ui.label(format!(
+            "{} stands for the name of the file flipped, an auto's paths included",
+            variants::NAME_PLACEHOLDER
+        ));
+        match self.variant_outputs(picked) {
+            None => {
+                ui.label(RichText::new("no output folder chosen").color(egui::Color32::RED));
+            }
+            Some(Err(err)) => {
+                ui.label(
+                    RichText::new(err.to_string())
+                        .monospace()
+                        .color(egui::Color32::RED),
+                );
+            }
+            Some(Ok(flips)) => {
+                for flip in &flips {
+                    let outputs =
+                        std::iter::once(&flip.output).chain(flip.paths.iter().map(|(_, o)| o));
+                    for output in outputs {
+                        ui.label(
+                            RichText::new(format!("{:<13}  {}", flip.variant, output.display()))
+                                .monospace()
+                                .color(if name_valid(output) {
+                                    egui::Color32::GREEN
+                                } else {
+                                    egui::Color32::RED
+                                }),
+                        );
                     }
                 }
             }
         }
+        changed
+    }
+
+    // plans every selected variant for confirmation, once their names are all usable
+    fn variants_button(&mut self, ui: &mut egui::Ui, picked: &Path) {
+        let Some(Ok(flips)) = self.variant_outputs(picked) else {
+            return;
+        };
+        let names_valid = flips
+            .iter()
+            .all(|f| name_valid(&f.output) && f.paths.iter().all(|(_, output)| name_valid(output)));
+        if flips.is_empty()
+            || !names_valid
+            || !self.path_is_valid_file
+            || !(self.plotter.warnings().is_empty() || self.flip_anyway)
+        {
+            return;
+        }
+        if ui
+            .button(format!("Make {} variant(s)", flips.len()))
+            .clicked()
+        {
+            let mut plan = plan::WritePlan::default();
+            self.write_status.clear();
+            match variants::plan_variants(picked, &flips, &self.flip_options(), &mut plan) {
+                Ok(()) => {
+                    self.pending_plan = Some(plan);
+                    self.allow_overwrite = false;
+                }
+                Err(err) => self.errors.push(err),
+            }
+        }
+    }
+
+    // picks a file to check as a mirror of `picked`, and shows how far off it is
+    fn compare_ui(&mut self, ui: &mut egui::Ui, picked: &Path) {
+        ui.horizontal(|ui| {
+            if ui.button("Compare with a mirror...").clicked() {
+                let ext = self.path_type.get_ext();
+                let mut dialog = rfd::FileDialog::new().add_filter(&ext, &[&ext]);
+                if let Some(dir) = picked.parent() {
+                    dialog = dialog.set_directory(dir);
+                }
+                if let Some(mirror) = dialog.pick_file() {
+                    self.symmetry = None;
+                    match symmetry::compare(picked, &mirror) {
+                        Ok(report) => self.symmetry = Some(report),
+                        Err(err) => self.errors.push(err),
+                    }
+                    self.recalc_path = true;
+                }
+            }
+            if self.symmetry.is_some() && ui.button("Clear comparison").clicked() {
+                self.symmetry = None;
+                self.recalc_path = true;
+            }
+        });
+        if let Some(report) = &self.symmetry {
+            ui.label(RichText::new(report.to_string().trim_end()).monospace());
+        }
+    }
+
+    fn try_load_file(&mut self, path: &Path) -> error::Result<()> {
+        let ext = path.extension().unwrap_or(OsStr::new(""));
+        if ext
+            == (FlipFileType::PathplannerAuto { is_chor: false })
+                .get_ext()
+                .as_str()
+        {
+            let data = migrate::load::<pathplanner::auto::AutoData>(path)?.data;
+            let names = data.get_filenames();
+            self.auto_files =
+                project::Layout::discover(path).auto_paths(path, &names.0, names.1)?;
+            self.auto_file_names = self.auto_files.iter().map(|_| String::new()).collect();
+            self.auto_file_valids = self.auto_files.iter().map(|_| false).collect();
+            self.path_type = FlipFileType::PathplannerAuto { is_chor: names.1 };
+            self.path_is_valid_file = true;
+            self.recalc_path = true;
+        } else {
+            for i in [FlipFileType::Choreo, FlipFileType::Pathplanner] {
+                if i.get_ext().as_str() == ext {
+                    i.check_file(path)?;
+                    self.path_is_valid_file = true;
+                    self.recalc_path = true;
+                    self.path_type = i;
+                    self.auto_files.clear();
+                }
+            }
+        }
+        Ok(())
     }
 }
 
@@ -231,6 +608,22 @@ impl eframe::App for PathFlip {
             self.chassis_color[1],
             self.chassis_color[2],
         );
+        if let Some(watcher) = &mut self.watcher {
+            match watcher.sync() {
+                Ok(Some(rebuilt)) => {
+                    self.write_status = format!("Regenerated {} file(s)", rebuilt.len());
+                    self.recalc_path = true;
+                }
+                Ok(None) => {}
+                // keep watching, the next save may fix it. Once is enough to show it
+                Err(err) => {
+                    if self.errors.last().map(|e| e.to_string()) != Some(err.to_string()) {
+                        self.errors.push(err);
+                    }
+                }
+            }
+            ctx.request_repaint_after(watch::POLL_INTERVAL);
+        }
         if self.modal_open {
             egui::Window::new("Config Options")
                 .collapsible(false)
@@ -300,6 +693,56 @@ impl eframe::App for PathFlip {
                         });
                 });
         }
+        if let Some(plan) = &self.pending_plan {
+            let mut write = false;
+            let mut cancel = false;
+            egui::Window::new("Confirm Writes")
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    for w in &plan.writes {
+                        ui.label(
+                            RichText::new(format!("{:<9} {}", w.change, w.path.display()))
+                                .monospace()
+                                .color(match w.change {
+                                    plan::Change::Create => egui::Color32::GREEN,
+                                    plan::Change::Modify => egui::Color32::YELLOW,
+                                    plan::Change::Overwrite => egui::Color32::RED,
+                                }),
+                        );
+                    }
+                    let overwrites = plan.overwrites();
+                    if overwrites > 0 {
+                        ui.checkbox(
+                            &mut self.allow_overwrite,
+                            format!("Overwrite {} existing file(s)", overwrites),
+                        );
+                    }
+                    ui.horizontal(|ui| {
+                        write = ui
+                            .add_enabled(
+                                overwrites == 0 || self.allow_overwrite,
+                                egui::Button::new("Write"),
+                            )
+                            .clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            if write {
+                match plan.commit() {
+                    Ok(()) => {
+                        self.write_status = format!("Wrote {} file(s)", plan.writes.len());
+                        // the writes may have moved the open file or its project's field
+                        self.refresh_calibration();
+                        self.recalc_path = true;
+                    }
+                    Err(err) => self.errors.push(err),
+                }
+            }
+            if write || cancel {
+                self.pending_plan = None;
+            }
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             if ui.button("Config").clicked() {
                 self.modal_open = true;
@@ -359,6 +802,47 @@ impl eframe::App for PathFlip {
                     }
                 }
 
+                if ui.button("Regenerate flipped files").clicked() {
+                    if let Some(manifest) = self.project_manifest() {
+                        self.regenerate(&manifest);
+                    }
+                }
+                if ui.button("Check flipped files").clicked() {
+                    if let Some(manifest) = self.project_manifest() {
+                        self.check(&manifest);
+                    }
+                }
+                if ui.button("Calibrate to a venue...").clicked() {
+                    let profile = rfd::FileDialog::new()
+                        .add_filter("json", &["json"])
+                        .pick_file();
+                    if let Some(profile) = profile {
+                        match calibration::FieldProfile::load(&profile) {
+                            Ok(profile) => {
+                                if let Some(manifest) = self.project_manifest() {
+                                    self.calibrate(&manifest, Some(profile));
+                                }
+                            }
+                            Err(err) => self.errors.push(err),
+                        }
+                    }
+                }
+                if ui.button("Back to the nominal field").clicked() {
+                    if let Some(manifest) = self.project_manifest() {
+                        self.calibrate(&manifest, None);
+                    }
+                }
+                let mut watching = self.watcher.is_some();
+                if ui.checkbox(&mut watching, "Regenerate on save").changed() {
+                    self.watcher = None;
+                    if let Some(manifest) = watching.then(|| self.project_manifest()).flatten() {
+                        match watch::Watcher::new(manifest) {
+                            Ok(watcher) => self.watcher = Some(watcher),
+                            Err(err) => self.errors.push(err),
+                        }
+                    }
+                }
+
                 if self.picked_path.is_some() {
                     if ui.button("Close File").clicked() {
                         self.picked_path = None;
@@ -367,6 +851,9 @@ impl eframe::App for PathFlip {
                         self.outputname.clear();
                         self.outputname_valid = false;
                         self.write_status.clear();
+                        self.errors.clear();
+                        self.pending_plan = None;
+                        self.symmetry = None;
                         ctx.style_mut(|f| {
                             f.visuals.override_text_color = None;
                         });
@@ -374,10 +861,10 @@ impl eframe::App for PathFlip {
                 }
             });
 
-            if let Some(picked_path) = &self.picked_path {
+            if let Some(picked_path) = self.picked_path.clone() {
                 ui.horizontal(|ui| {
                     ui.label("Selected file:");
-                    ui.label(RichText::new(picked_path).monospace().color(
+                    ui.label(RichText::new(&picked_path).monospace().color(
                         if self.path_is_valid_file {
                             egui::Color32::GREEN
                         } else {
@@ -385,42 +872,193 @@ impl eframe::App for PathFlip {
                         },
                     ));
                 });
+                ui.label(match &self.calibration {
+                    Some(profile) => format!(
+                        "Field: {} ({} x {} m)",
+                        profile.name, profile.length, profile.width
+                    ),
+                    None => "Field: nominal".to_owned(),
+                });
+                if self.path_is_valid_file
+                    && matches!(
+                        self.path_type,
+                        FlipFileType::Choreo | FlipFileType::Pathplanner
+                    )
+                {
+                    self.compare_ui(ui, Path::new(&picked_path));
+                }
             }
 
+            let mut custom = !self.transform_steps.is_empty();
+            let in_place = self.flip_options().in_place;
+            if ui
+                .add_enabled(
+                    !custom && !in_place && !self.make_variants,
+                    egui::Checkbox::new(
+                        &mut self.flip_same_alliance,
+                        "Flip across the Y axis (same alliance right/left)",
+                    ),
+                )
+                .changed()
+            {
+                self.recalc_path = true;
+            }
+            if ui
+                .add_enabled(
+                    !custom && !in_place && (!self.flip_same_alliance || self.make_variants),
+                    egui::Checkbox::new(
+                        &mut self.mirror_headings,
+                        "Mirror headings across the center line when flipping to the other \
+                         alliance",
+                    ),
+                )
+                .changed()
+            {
+                self.recalc_path = true;
+            }
+            if ui
+                .add_enabled(
+                    !self.make_variants,
+                    egui::Checkbox::new(
+                        &mut custom,
+                        "Custom transform (translate, rotate and reflect instead of flipping)",
+                    ),
+                )
+                .changed()
+            {
+                self.transform_steps.clear();
+                if custom {
+                    self.transform_steps
+                        .push(flip::TransformStep::Translate { x: 0.0, y: 0.0 });
+                }
+                self.recalc_path = true;
+            }
+            if custom && !self.make_variants && self.transform_ui(ui) {
+                self.recalc_path = true;
+            }
+            // autos would have to run their commands backwards too
+            let is_auto = matches!(self.path_type, FlipFileType::PathplannerAuto { .. });
+            if ui
+                .add_enabled(
+                    !is_auto,
+                    egui::Checkbox::new(
+                        &mut self.reverse,
+                        "Reverse, so it runs from its end to its start",
+                    ),
+                )
+                .changed()
+            {
+                self.recalc_path = true;
+            }
+            if ui
+                .add_enabled(
+                    !is_auto && self.reverse && !custom && !self.make_variants,
+                    egui::Checkbox::new(
+                        &mut self.reverse_in_place,
+                        "Only reverse it, without flipping it",
+                    ),
+                )
+                .changed()
+            {
+                self.recalc_path = true;
+            }
             if ui
                 .checkbox(
-                    &mut self.flip_same_alliance,
-                    "Flip across the Y axis (same alliance right/left)",
+                    &mut self.make_variants,
+                    "Make several variants at once, each named by its own rule",
                 )
                 .changed()
             {
                 self.recalc_path = true;
             }
+            if matches!(
+                self.path_type,
+                FlipFileType::Choreo | FlipFileType::PathplannerAuto { is_chor: true }
+            ) {
+                ui.horizontal(|ui| {
+                    ui.label("Time scale");
+                    ui.add(
+                        egui::DragValue::new(&mut self.time_scale)
+                            .speed(0.01)
+                            .range(0.1..=10.0),
+                    );
+                    ui.checkbox(&mut self.resample, "Resample to dt (s)");
+                    ui.add_enabled(
+                        self.resample,
+                        egui::DragValue::new(&mut self.resample_dt)
+                            .speed(0.001)
+                            .range(0.005..=0.5),
+                    );
+                });
+                ui.checkbox(
+                    &mut self.mirror_variables,
+                    "Add mirrored copies of referenced project variables",
+                );
+            }
+            ui.horizontal(|ui| {
+                ui.label("Round lengths to");
+                ui.add(
+                    egui::DragValue::new(&mut self.round_length_digits)
+                        .range(1..=15)
+                        .prefix("1e-")
+                        .suffix(" m"),
+                );
+                ui.label("angles to");
+                ui.add(
+                    egui::DragValue::new(&mut self.round_angle_digits)
+                        .range(1..=15)
+                        .prefix("1e-")
+                        .suffix(" rad"),
+                );
+            });
+            ui.checkbox(
+                &mut self.write_latest,
+                format!(
+                    "Write the latest file version (Choreo {}, PathPlanner {})",
+                    migrate::CHOREO_VERSION,
+                    migrate::PATHPLANNER_VERSION
+                ),
+            );
             ui.checkbox(
                 &mut self.use_curr_dir,
                 "Use Selected Path Directory for output",
             );
-            if let Some(picked_p) = &self.picked_path {
+            if !self.use_curr_dir {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose output folder").clicked() {
+                        let mut dialog = rfd::FileDialog::new();
+                        let start = self.output_dir.clone().or_else(|| {
+                            let picked = Path::new(self.picked_path.as_ref()?);
+                            picked.parent().map(Path::to_path_buf)
+                        });
+                        if let Some(dir) = start {
+                            dialog = dialog.set_directory(dir);
+                        }
+                        if let Some(dir) = dialog.pick_folder() {
+                            self.output_dir = Some(dir);
+                        }
+                    }
+                    match &self.output_dir {
+                        Some(dir) => ui.label(dir.display().to_string()),
+                        None => {
+                            ui.label(RichText::new("no folder chosen").color(egui::Color32::RED))
+                        }
+                    };
+                });
+            }
+            if let Some(picked_p) = self.picked_path.clone().filter(|_| self.make_variants) {
+                if self.path_is_valid_file && self.variants_ui(ui, Path::new(&picked_p)) {
+                    self.recalc_path = true;
+                }
+            } else if let Some(picked_p) = self.picked_path.clone() {
+                let picked_p = PathBuf::from(picked_p);
                 let outputnamelabel = ui.label(format!(
                     "Output file name -- {}",
-                    PathBuf::from(picked_p)
-                        .file_name()
-                        .unwrap()
-                        .display()
-                        .to_string()
+                    picked_p.file_name().unwrap_or_default().display()
                 ));
-                if self.use_curr_dir {
-                    self.dir_prefx = PathBuf::from(picked_p)
-                        .parent()
-                        .unwrap()
-                        .display()
-                        .to_string()
-                        + "\\";
-                } else {
-                    self.dir_prefx = "C:\\".to_string();
-                }
+                let output_dir = self.output_dir_for(&picked_p);
                 ui.horizontal(|ui| {
-                    ui.label(&self.dir_prefx);
+                    ui.label(dir_label(output_dir.as_deref()));
                     ui.add_enabled(
                         self.path_is_valid_file && self.picked_path.is_some(),
                         egui::TextEdit::singleline(&mut self.outputname).background_color(
@@ -438,29 +1076,23 @@ impl eframe::App for PathFlip {
                     is_chor: is_chorchor,
                 } = self.path_type
                 {
+                    let ext = if is_chorchor { "traj" } else { "path" };
                     for i in 0..self.auto_files.len() {
                         let sublabelname = ui.label(format!(
                             "Path {} -- {}",
                             i + 1,
-                            self.auto_files[i]
-                                .file_name()
-                                .unwrap()
-                                .display()
-                                .to_string()
+                            self.auto_files[i].file_name().unwrap_or_default().display()
                         ));
-                        if self.use_curr_dir {
-                            self.auto_file_prefs[i] =
-                                self.auto_files[i].parent().unwrap().display().to_string() + "\\";
-                        } else {
-                            self.auto_file_prefs[i] = "C:\\".to_string();
-                        }
-                        self.auto_file_valids[i] = outputfile_valid_list(
+                        let path_dir = self.output_dir_for(&self.auto_files[i]);
+                        self.auto_file_valids[i] = output_valid(
                             &self.auto_file_names[i],
-                            &self.auto_files[i].display().to_string(),
+                            ext,
+                            path_dir.as_deref(),
+                            &self.auto_files[i],
                             &self.auto_file_names[0..i],
                         );
                         ui.horizontal(|ui| {
-                            ui.label(&self.auto_file_prefs[i]);
+                            ui.label(dir_label(path_dir.as_deref()));
                             ui.add_enabled(
                                 self.path_is_valid_file && self.picked_path.is_some(),
                                 egui::TextEdit::singleline(&mut self.auto_file_names[i])
@@ -471,7 +1103,7 @@ impl eframe::App for PathFlip {
                                     }),
                             )
                             .labelled_by(sublabelname.id);
-                            ui.label(".".to_string() + if is_chorchor { "traj" } else { "path" });
+                            ui.label(".".to_string() + ext);
                         });
                     }
                 }
@@ -480,18 +1112,56 @@ impl eframe::App for PathFlip {
                 ui.label(
                     RichText::new(&self.write_status)
                         .monospace()
-                        .color(if self.write_err {
-                            egui::Color32::RED
-                        } else {
-                            egui::Color32::GREEN
-                        }),
+                        .color(egui::Color32::GREEN),
+                );
+            }
+            if !self.errors.is_empty() {
+                egui::Frame::group(ui.style())
+                    .stroke(egui::Stroke::new(1.0, egui::Color32::RED))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("Errors").strong().color(egui::Color32::RED));
+                            if ui.button("Clear").clicked() {
+                                self.errors.clear();
+                            }
+                        });
+                        for err in &self.errors {
+                            ui.label(
+                                RichText::new(err.to_string())
+                                    .monospace()
+                                    .color(egui::Color32::RED),
+                            );
+                        }
+                    });
+            }
+            if self.path_is_valid_file && !self.plotter.warnings().is_empty() {
+                ui.label(
+                    RichText::new(
+                        "Trajectory is out of date with its Choreo params or project, \
+                         Choreo will regenerate it differently:",
+                    )
+                    .color(egui::Color32::YELLOW),
                 );
+                for warning in self.plotter.warnings() {
+                    ui.label(
+                        RichText::new(format!("  {}", warning))
+                            .monospace()
+                            .color(egui::Color32::YELLOW),
+                    );
+                }
+                ui.checkbox(&mut self.flip_anyway, "Flip anyway");
             }
-            self.outputname_valid = outputfile_valid(
+            let picked = PathBuf::from(self.picked_path.clone().unwrap_or_default());
+            self.outputname_valid = output_valid(
                 &self.outputname,
-                self.picked_path.as_ref().unwrap_or(&String::new()),
+                &self.path_type.get_ext(),
+                self.output_dir_for(&picked).as_deref(),
+                &picked,
+                &[],
             );
-            if let Some(path) = &self.picked_path {
+            if self.make_variants {
+                self.variants_button(ui, &picked);
+            } else if let Some(path) = &self.picked_path {
                 if self.path_is_valid_file
                     && self.outputname_valid
                     && !path.is_empty()
@@ -500,39 +1170,76 @@ impl eframe::App for PathFlip {
                         FlipFileType::PathplannerAuto { is_chor: false }
                             | FlipFileType::PathplannerAuto { is_chor: true }
                     ) || self.auto_file_valids.iter().all(|b| *b))
+                    && (self.plotter.warnings().is_empty() || self.flip_anyway)
                 {
                     if ui.button("Flip").clicked() {
-                        let mut outputfile = PathBuf::from(&self.dir_prefx);
-                        outputfile.push(&self.outputname);
-                        outputfile.set_extension(self.path_type.get_ext());
-                        let stat = self.plotter.send_flip(
-                            self.picked_path.as_ref().unwrap().to_owned(),
-                            outputfile.display().to_string(),
-                            self.flip_same_alliance,
-                            Some(&self.auto_file_names),
-                        );
-                        self.write_status = format!("{:?}", stat);
-                        self.write_err = stat.is_err();
+                        // valid names always have an output folder
+                        let outputfile = self
+                            .output_dir_for(&picked)
+                            .unwrap_or_default()
+                            .join(format!("{}.{}", self.outputname, self.path_type.get_ext()));
+                        let opts = self.flip_options();
+                        let mut plan = plan::WritePlan::default();
+                        let pairs = self.auto_pairs().unwrap_or_default();
+                        let stat =
+                            manifest::plan_flip(&picked, &outputfile, &pairs, &opts, &mut plan)
+                                .and_then(|()| {
+                                    manifest::record_flip(
+                                        &picked,
+                                        &outputfile,
+                                        &pairs,
+                                        &opts,
+                                        &mut plan,
+                                    )
+                                });
+                        self.write_status.clear();
+                        match stat {
+                            Ok(()) => {
+                                self.pending_plan = Some(plan);
+                                self.allow_overwrite = false;
+                            }
+                            Err(err) => self.errors.push(err),
+                        }
                     }
                 }
             }
+            // variants are previewed over the first of them
+            let opts = self.flip_options();
+            let variants: Vec<(String, flip::Transform2d)> = self
+                .variant_rules()
+                .into_iter()
+                .filter(|_| self.make_variants)
+                .map(|(v, _)| (v.to_string(), v.options(&opts).transform()))
+                .collect();
+            let transform = match variants.first() {
+                Some((_, transform)) => *transform,
+                None => opts.transform(),
+            };
             if let Some(picked_pth) = &mut self.picked_path {
                 if self.path_is_valid_file {
                     if self.recalc_path {
                         self.plotter.reset();
                         self.plotter
                             .set_plot_type(&self.path_type, self.auto_files.clone());
-                        self.plotter
-                            .gen(
-                                picked_pth,
-                                self.robot_y_m,
-                                self.robot_x_m,
-                                self.flip_same_alliance,
-                            )
-                            .unwrap();
+                        if let Err(err) = self.plotter.gen(
+                            Path::new(picked_pth),
+                            self.robot_y_m,
+                            self.robot_x_m,
+                            &transform,
+                        ) {
+                            self.errors.push(err);
+                            self.path_is_valid_file = false;
+                        }
+                        if let Some(report) = &self.symmetry {
+                            self.plotter.show_deviations(report.best());
+                        }
+                        self.plotter.show_variants(&variants);
                         self.recalc_path = false;
                     }
-                    self.plotter.plot(&col, ctx, ui).unwrap();
+                    if let Err(err) = self.plotter.plot(&col, ctx, ui) {
+                        self.errors.push(err);
+                        self.path_is_valid_file = false;
+                    }
                 }
             }
         });
@@ -547,71 +1254,53 @@ impl eframe::App for PathFlip {
     }
 }
 
-fn outputfile_valid(name: &String, inputname: &String) -> bool {
-    !name.is_empty()
-        && name != inputname
-        && !name
-            .chars()
-            .any(|c| matches!(c, '.' | '<' | '>' | ':' | '"' | '/' | '|' | '?' | '*'))
-        && !matches!(
-            name.as_str(),
-            "CON"
-                | "PRN"
-                | "AUX"
-                | "NUL"
-                | "COM1"
-                | "COM2"
-                | "COM3"
-                | "COM4"
-                | "COM5"
-                | "COM6"
-                | "COM7"
-                | "COM8"
-                | "COM9"
-                | "LPT1"
-                | "LPT2"
-                | "LPT3"
-                | "LPT4"
-                | "LPT5"
-                | "LPT6"
-                | "LPT7"
-                | "LPT8"
-                | "LPT9"
-        )
+// the folder an output goes in, as shown before its name
+fn dir_label(dir: Option<&Path>) -> String {
+    match dir {
+        Some(dir) => format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR),
+        None => String::new(),
+    }
+}
+
+// `name` + `ext` makes a new file in `dir` that isn't the input and isn't used by another
+// output
+fn output_valid(name: &str, ext: &str, dir: Option<&Path>, input: &Path, taken: &[String]) -> bool {
+    let Some(dir) = dir else {
+        return false;
+    };
+    filename_valid(name)
+        && !taken.iter().any(|t| t == name)
+        && dir.join(format!("{}.{}", name, ext)) != input
+}
+
+// an output named by a variant rule, which has to be a name `filename_valid` accepts
+fn name_valid(output: &Path) -> bool {
+    filename_valid(&output.file_stem().unwrap_or_default().to_string_lossy())
 }
 
-fn outputfile_valid_list(name: &String, inputname: &String, last_names: &[String]) -> bool {
-    !name.is_empty()
-        && inputname != name
-        && !last_names.contains(name)
-        && !name
-            .chars()
-            .any(|c| matches!(c, '.' | '<' | '>' | ':' | '"' | '/' | '|' | '?' | '*'))
-        && !matches!(
-            name.as_str(),
-            "CON"
-                | "PRN"
-                | "AUX"
-                | "NUL"
-                | "COM1"
-                | "COM2"
-                | "COM3"
-                | "COM4"
-                | "COM5"
-                | "COM6"
-                | "COM7"
-                | "COM8"
-                | "COM9"
-                | "LPT1"
-                | "LPT2"
-                | "LPT3"
-                | "LPT4"
-                | "LPT5"
-                | "LPT6"
-                | "LPT7"
-                | "LPT8"
-                | "LPT9"
-        )
+// names the host OS can create. Dots are left out on every OS since pathplanner autos use
+// "name.N" for choreo splits
+fn filename_valid(name: &str) -> bool {
+    if name.is_empty() || name.contains(['.', '/', '\0']) {
+        return false;
+    }
+    if cfg!(windows) {
+        let stem = name.to_ascii_uppercase();
+        let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+            || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && stem.ends_with(|c: char| c.is_ascii_digit() && c != '0'));
+        !reserved
+            && !name.ends_with(' ')
+            && !name.chars().any(|c| {
+                c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*')
+            })
+    } else if cfg!(target_os = "macos") {
+        // finder shows ':' as '/', and the older APIs refuse it
+        !name.contains(':')
+    } else {
+        true
+    }
 }
 
 fn collect_files(folder: &Path, extensions: &[&str]) -> Vec<DroppedFile> {
@@ -674,3 +1363,43 @@ fn drop_file_preview(ctx: &egui::Context) {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_need_a_new_usable_name() {
+        let dir = Path::new("deploy/pathplanner/paths");
+        let input = dir.join("Start.path");
+        let taken = ["Other".to_owned()];
+        assert!(output_valid(
+            "Start Flipped",
+            "path",
+            Some(dir),
+            &input,
+            &taken
+        ));
+        // no folder picked yet
+        assert!(!output_valid("Start Flipped", "path", None, &input, &taken));
+        // the file being flipped, or another output's name
+        assert!(!output_valid("Start", "path", Some(dir), &input, &taken));
+        assert!(!output_valid("Other", "path", Some(dir), &input, &taken));
+        // a dot would read as a choreo split, a slash as a folder
+        for name in ["", "Start.1", "paths/Start"] {
+            assert!(!filename_valid(name), "{:?}", name);
+        }
+        assert!(name_valid(&dir.join("Start Flipped.path")));
+        assert_eq!(dir_label(None), "");
+        assert!(dir_label(Some(dir)).ends_with(std::path::MAIN_SEPARATOR));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_names_are_checked() {
+        for name in ["CON", "com1", "a:b", "trailing "] {
+            assert!(!filename_valid(name), "{:?}", name);
+        }
+        assert!(filename_valid("COM0") && filename_valid("CONSOLE"));
+    }
+}